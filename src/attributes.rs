@@ -0,0 +1,196 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::dates::{format_duration, parse_duration};
+use crate::filter::{parse_date, FIELD_NAMES};
+use crate::tasks::{Task, SORT_KEYS};
+
+/// Custom attributes declared under `attributes` in the config, keyed by name.
+pub(crate) type Attributes = BTreeMap<String, Attribute>;
+
+/// Names built-in filters and the fields of the 'edit' form already use, besides the date fields and sort keys.
+const RESERVED_NAMES: [&str; 11] = ["project", "tag", "tags", "context", "title", "description", "estimate", "remind", "flagged", "status", "notes"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AttributeType {
+    String,
+    Number,
+    Date,
+    Duration,
+    /// One of the attribute's declared `values`.
+    Enum,
+}
+
+/// A typed field teams can add to tasks, e.g. `"severity": { "type": "enum", "values": ["low", "high"] }`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Attribute {
+    #[serde(rename = "type")]
+    pub kind: AttributeType,
+    /// Allowed values of an enum, lowest first.
+    #[serde(default)]
+    pub values: Vec<String>,
+}
+
+impl Attribute {
+    /// Validates `raw` against the attribute's type and returns the value as it's stored: dates as
+    /// YYYY-MM-DD, durations like 1h30m and enum values spelled as declared.
+    pub(crate) fn parse(&self, name: &str, raw: &str) -> Result<String, String> {
+        match self.kind {
+            AttributeType::String => Ok(raw.to_string()),
+            AttributeType::Number => raw.parse::<f64>()
+                .ok()
+                .filter(|number| number.is_finite())
+                .map(|number| number.to_string())
+                .ok_or_else(|| format!("{}: '{}' is not a number.", name, raw)),
+            AttributeType::Date => parse_date(raw).map(|date| date.as_iso_date_string()).map_err(|error| format!("{}: {}", name, error)),
+            AttributeType::Duration => parse_duration(raw).map(|duration| format_duration(duration).replace(' ', "")).map_err(|error| format!("{}: {}", name, error)),
+            AttributeType::Enum => self.values.iter()
+                .find(|value| value.eq_ignore_ascii_case(raw))
+                .cloned()
+                .ok_or_else(|| format!("{}: '{}' is not one of {}.", name, raw, self.values.join(", "))),
+        }
+    }
+
+    /// Orders two stored values. Enum values follow their declared order.
+    pub(crate) fn compare(&self, a: &str, b: &str) -> Ordering {
+        match self.kind {
+            AttributeType::String => a.to_lowercase().cmp(&b.to_lowercase()),
+            AttributeType::Number => {
+                let number = |value: &str| value.parse::<f64>().unwrap_or(0.0);
+                number(a).total_cmp(&number(b))
+            }
+            // Stored as YYYY-MM-DD, which sorts as text.
+            AttributeType::Date => a.cmp(b),
+            AttributeType::Duration => {
+                let minutes = |value: &str| parse_duration(value).map(|duration| duration.num_minutes()).unwrap_or(0);
+                minutes(a).cmp(&minutes(b))
+            }
+            AttributeType::Enum => {
+                let position = |value: &str| self.values.iter().position(|allowed| allowed == value);
+                position(a).cmp(&position(b))
+            }
+        }
+    }
+
+    /// Orders tasks by this attribute, tasks without it last.
+    pub(crate) fn compare_tasks(&self, name: &str, a: &Task, b: &Task) -> Ordering {
+        match (a.attributes.get(name), b.attributes.get(name)) {
+            (Some(a), Some(b)) => self.compare(a, b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+}
+
+/// Reports the first attribute whose name clashes with a built-in one or whose declaration is incomplete.
+pub(crate) fn check_attributes(attributes: &Attributes) -> Result<(), String> {
+    for (name, attribute) in attributes {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') {
+            return Err(format!("Attribute '{}': names may only use lowercase letters, digits and '_'.", name));
+        }
+        if FIELD_NAMES.contains(&name.as_str()) || SORT_KEYS.contains(&name.as_str()) || RESERVED_NAMES.contains(&name.as_str()) {
+            return Err(format!("Attribute '{}': the name is already used by TaskNinja.", name));
+        }
+        if attribute.kind == AttributeType::Enum && attribute.values.is_empty() {
+            return Err(format!("Attribute '{}': an enum needs a list of 'values'.", name));
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads a `name:value` assignment from 'add' or 'edit'. An empty value removes the attribute.
+pub(crate) fn parse_assignment(attributes: &Attributes, assignment: &str) -> Result<(String, Option<String>), String> {
+    let (name, raw) = assignment.split_once(':').ok_or_else(|| format!("Expected name:value, found '{}'.", assignment))?;
+    let attribute = attributes.get(name).ok_or_else(|| unknown(name, attributes))?;
+
+    match raw.trim() {
+        "" => Ok((name.to_string(), None)),
+        raw => attribute.parse(name, raw).map(|value| (name.to_string(), Some(value))),
+    }
+}
+
+fn unknown(name: &str, attributes: &Attributes) -> String {
+    match attributes.is_empty() {
+        true => format!("Unknown attribute '{}'. Declare custom attributes under 'attributes' in the config.", name),
+        false => format!("Unknown attribute '{}'. Expected one of: {}.", name, attributes.keys().cloned().collect::<Vec<String>>().join(", ")),
+    }
+}
+
+/// Whether `filter` has the shape of an attribute term, e.g. `customer:acme` or `points.above:3`.
+/// Which attributes exist is only known once the config is loaded, see `AttributeFilter::parse`.
+pub(crate) fn is_attribute_term(filter: &str) -> bool {
+    match filter.split_once(':') {
+        Some((key, _)) => {
+            let name = key.split_once('.').map(|(name, _)| name).unwrap_or(key);
+            !name.is_empty()
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !FIELD_NAMES.contains(&name)
+                && !RESERVED_NAMES.contains(&name)
+        }
+        None => false,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Equals,
+    Above,
+    Below,
+}
+
+/// A filter on a custom attribute: `name:value`, `name:` for tasks without it, or `name.above:value`
+/// and `name.below:value` for anything but strings.
+#[derive(Debug, Clone)]
+pub(crate) struct AttributeFilter {
+    name: String,
+    attribute: Attribute,
+    comparison: Comparison,
+    value: Option<String>,
+}
+
+impl AttributeFilter {
+    /// `None` when `filter` isn't a term on a declared attribute.
+    pub(crate) fn parse(filter: &str, attributes: &Attributes) -> Result<Option<AttributeFilter>, String> {
+        let (key, raw) = match filter.split_once(':') {
+            Some(parts) => parts,
+            None => return Ok(None),
+        };
+        let (name, comparison) = match key.split_once('.') {
+            Some((name, "above" | "after")) => (name, Comparison::Above),
+            Some((name, "below" | "before")) => (name, Comparison::Below),
+            Some((name, modifier)) if attributes.contains_key(name) => {
+                return Err(format!("Unknown modifier '{}' in '{}'. Expected above or below.", modifier, filter));
+            }
+            _ => (key, Comparison::Equals),
+        };
+        let attribute = match attributes.get(name) {
+            Some(attribute) => attribute.clone(),
+            None => return Ok(None),
+        };
+
+        if comparison != Comparison::Equals && attribute.kind == AttributeType::String {
+            return Err(format!("'{}' is a string and can't be compared with above or below.", name));
+        }
+        let value = match (raw, comparison) {
+            ("", Comparison::Equals) => None,
+            _ => Some(attribute.parse(name, raw)?),
+        };
+
+        Ok(Some(AttributeFilter { name: name.to_string(), attribute, comparison, value }))
+    }
+
+    pub(crate) fn matches(&self, task: &Task) -> bool {
+        match (task.attributes.get(&self.name), &self.value) {
+            (None, None) => true,
+            (Some(own), Some(value)) => match self.comparison {
+                Comparison::Equals => self.attribute.compare(own, value) == Ordering::Equal,
+                Comparison::Above => self.attribute.compare(own, value) == Ordering::Greater,
+                Comparison::Below => self.attribute.compare(own, value) == Ordering::Less,
+            },
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,443 @@
+use std::collections::HashMap;
+use chrono::Duration;
+use crate::{Date, Time};
+use crate::dates::parse_duration;
+use crate::filter::{parse_date, parse_ids};
+use crate::attributes::is_attribute_term;
+use crate::command_handler::{find_command, CommandError};
+use crate::tasks::{check_filter, Priority};
+
+/// Tokens that request help for any command.
+pub(crate) const HELP_FLAGS: [&str; 3] = ["help", "-h", "--help"];
+
+/// Options given before the command, with what they're for, e.g. `taskninja --list work add ...`.
+pub(crate) const GLOBAL_OPTIONS: [(&str, &str); 3] = [
+    ("--config FILE", "Read the config from FILE. (Or set TASKNINJA_CONFIG)"),
+    ("--data-file FILE", "Keep the current list's tasks in FILE. (Or set TASKNINJA_DATA)"),
+    ("--list NAME", "Run the command on task list NAME instead of the current one."),
+];
+
+/// Width of the flag column in generated help.
+const FLAG_COLUMN: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ValueType {
+    Text,
+    Number,
+    /// A 1-based task ID as shown by 'list'.
+    Id,
+    Date,
+    Time,
+    /// Hours and minutes, e.g. 1h30m.
+    Duration,
+    /// high, medium, low, or none to clear it.
+    Priority,
+    /// A filter name, date term or attribute term, see `TaskList::filter_tasks_with_attributes`.
+    Filter,
+    /// Task IDs and ranges like 1,3,5-9, or a filter selecting tasks.
+    Tasks,
+    Choice(&'static [&'static str]),
+    /// The name or alias of another command, or an alias from the config.
+    Command,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ArgKind {
+    Switch,
+    Value(ValueType),
+}
+
+/// A single argument a command accepts.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Arg {
+    pub(crate) name: &'static str,
+    pub(crate) flags: &'static [&'static str],
+    pub(crate) kind: ArgKind,
+    /// Whether a bare word fills this argument.
+    pub(crate) positional: bool,
+    /// Whether the argument can be given more than once. Every value is kept.
+    pub(crate) multiple: bool,
+    pub(crate) required: bool,
+    pub(crate) help: &'static str,
+}
+
+impl Arg {
+    pub(crate) const fn switch(name: &'static str, flags: &'static [&'static str], help: &'static str) -> Arg {
+        Arg { name, flags, kind: ArgKind::Switch, positional: false, multiple: false, required: false, help }
+    }
+
+    pub(crate) const fn value(name: &'static str, flags: &'static [&'static str], value_type: ValueType, help: &'static str) -> Arg {
+        Arg { name, flags, kind: ArgKind::Value(value_type), positional: false, multiple: false, required: false, help }
+    }
+
+    pub(crate) const fn positional(self) -> Arg {
+        Arg { positional: true, ..self }
+    }
+
+    pub(crate) const fn multiple(self) -> Arg {
+        Arg { multiple: true, ..self }
+    }
+
+    pub(crate) const fn required(self) -> Arg {
+        Arg { required: true, ..self }
+    }
+
+    /// Name used in error messages and usage lines.
+    pub(crate) fn label(&self) -> String {
+        match self.name {
+            "id" => "ID".to_string(),
+            name => capitalize(name),
+        }
+    }
+
+    fn help_line(&self) -> String {
+        let mut names = self.flags.join(", ");
+        if names.is_empty() {
+            names = self.label();
+        }
+
+        let padding = if names.len() < FLAG_COLUMN { FLAG_COLUMN - names.len() } else { 1 };
+        let mut line = format!("    {}{}{}", names, " ".repeat(padding), self.help);
+
+        line.push_str(if self.required { " (Required)" } else { " (Optional)" });
+        if self.positional && !self.flags.is_empty() {
+            line.push_str(&format!("\n    {} - Can be set without the {} flag.", " ".repeat(FLAG_COLUMN), self.flags.join(" or ")));
+        }
+
+        line
+    }
+}
+
+pub(crate) type Handler = fn(&Matches, &mut crate::TaskList, crate::Config) -> Result<String, CommandError>;
+
+/// A command, the arguments it takes and how it's run.
+pub(crate) struct Command {
+    pub(crate) name: &'static str,
+    pub(crate) aliases: &'static [&'static str],
+    pub(crate) summary: &'static str,
+    pub(crate) args: &'static [Arg],
+    pub(crate) examples: &'static [&'static str],
+    /// Extra help text shown after the arguments.
+    pub(crate) notes: &'static str,
+    pub(crate) handler: Handler,
+}
+
+impl Command {
+    /// Name used in error messages, e.g. 'Add'.
+    pub(crate) fn display_name(&self) -> String {
+        capitalize(self.name)
+    }
+
+    pub(crate) fn matches_name(&self, name: &str) -> bool {
+        self.name == name || self.aliases.contains(&name)
+    }
+
+    /// Every flag the command accepts, including the help flags.
+    pub(crate) fn flags(&self) -> Vec<&'static str> {
+        let mut flags: Vec<&'static str> = HELP_FLAGS[1..].to_vec();
+        flags.extend(self.args.iter().flat_map(|arg| arg.flags.iter().copied()));
+        flags
+    }
+
+    /// The type of the first bare word, if the command takes one.
+    pub(crate) fn positional_type(&self) -> Option<ValueType> {
+        self.args.iter().find(|arg| arg.positional).and_then(|arg| match arg.kind {
+            ArgKind::Value(value_type) => Some(value_type),
+            ArgKind::Switch => None,
+        })
+    }
+
+    /// The argument the word after `previous` fills, the way `parse` would assign it, and whether
+    /// that word has to be a value because it follows a flag.
+    pub(crate) fn next_arg(&self, previous: &[&str]) -> Option<(&Arg, bool)> {
+        let mut filled: Vec<&str> = Vec::new();
+        let mut tokens = previous.iter();
+
+        while let Some(token) = tokens.next() {
+            match self.args.iter().find(|arg| arg.flags.contains(token)) {
+                Some(arg) if arg.kind == ArgKind::Switch => {}
+                Some(arg) => {
+                    if tokens.next().is_none() {
+                        return Some((arg, true));
+                    }
+                }
+                None => {
+                    if let Some(arg) = self.args.iter().find(|arg| arg.positional && (arg.multiple || !filled.contains(&arg.name))) {
+                        filled.push(arg.name);
+                    }
+                }
+            }
+        }
+
+        self.args.iter()
+            .find(|arg| arg.positional && (arg.multiple || !filled.contains(&arg.name)))
+            .map(|arg| (arg, false))
+    }
+
+    fn usage(&self) -> String {
+        let mut usage = format!("Usage: taskninja {}", self.name);
+        for arg in self.args.iter().filter(|arg| arg.positional) {
+            let name = if arg.name == "id" { arg.label() } else { arg.name.to_string() };
+            usage.push_str(&format!(" [{}{}]", name, if arg.multiple { "..." } else { "" }));
+        }
+        if self.args.iter().any(|arg| !arg.positional || !arg.flags.is_empty()) {
+            usage.push_str(" [options]");
+        }
+        usage
+    }
+
+    pub(crate) fn help(&self) -> String {
+        let mut help = format!("taskninja {}: {}\n{}\n\nArguments for '{}':\n", self.name, self.summary, self.usage(), self.name);
+
+        help.push_str(&format!("    {:<width$}Display detailed help about the {} operation.\n", HELP_FLAGS.join(", "), self.name, width = FLAG_COLUMN));
+        for arg in self.args.iter() {
+            help.push_str(&arg.help_line());
+            help.push('\n');
+        }
+
+        if !self.notes.is_empty() {
+            help.push('\n');
+            help.push_str(self.notes);
+        }
+
+        if !self.examples.is_empty() {
+            help.push_str("\nExamples:\n");
+            for example in self.examples {
+                help.push_str(&format!("    {}\n", example));
+            }
+        }
+
+        help
+    }
+
+    /// Parses the arguments following the command name.
+    pub(crate) fn parse(&self, arguments: &[String]) -> Result<Matches, CommandError> {
+        let operation = self.display_name();
+        let mut matches = Matches::default();
+
+        let mut i = 0;
+        while i < arguments.len() {
+            let token = arguments[i].as_str();
+
+            if HELP_FLAGS.contains(&token) {
+                matches.help = true;
+                return Ok(matches);
+            }
+
+            let flagged = self.args.iter().find(|arg| arg.flags.contains(&token));
+            let arg = match flagged {
+                Some(arg) => arg,
+                None => self.args.iter()
+                    .find(|arg| arg.positional && (arg.multiple || !matches.values.contains_key(arg.name)))
+                    .ok_or_else(|| CommandError::InvalidArgument(operation.clone(), token.to_string()))?,
+            };
+
+            let value = match arg.kind {
+                ArgKind::Switch => Value::Switch,
+                ArgKind::Value(value_type) => {
+                    let raw = if flagged.is_some() {
+                        i += 1;
+                        arguments.get(i).ok_or_else(|| CommandError::MissingRequiredArgument(operation.clone(), arg.label()))?
+                    } else {
+                        &arguments[i]
+                    };
+                    Value::parse(raw, value_type).map_err(|reason| CommandError::InvalidValue(operation.clone(), arg.label(), reason))?
+                }
+            };
+
+            if arg.multiple {
+                if let Value::List(values) = matches.values.entry(arg.name).or_insert_with(|| Value::List(Vec::new())) {
+                    values.push(value);
+                }
+            } else {
+                matches.values.insert(arg.name, value);
+            }
+            i += 1;
+        }
+
+        if let Some(missing) = self.args.iter().find(|arg| arg.required && !matches.values.contains_key(arg.name)) {
+            return Err(CommandError::MissingRequiredArgument(operation, missing.label()));
+        }
+
+        Ok(matches)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Value {
+    Switch,
+    Text(String),
+    Number(u32),
+    /// 0-based index into `TaskList::tasks`.
+    Id(usize),
+    Date(Date),
+    Time(Time),
+    Duration(Duration),
+    Priority(Option<Priority>),
+    /// Every value of an argument that can be given more than once.
+    List(Vec<Value>),
+}
+
+impl Value {
+    fn parse(raw: &str, value_type: ValueType) -> Result<Value, String> {
+        match value_type {
+            ValueType::Text => Ok(Value::Text(raw.to_string())),
+            ValueType::Number => raw.parse::<u32>().map(Value::Number).map_err(|_| format!("'{}' is not a number.", raw)),
+            ValueType::Id => match raw.parse::<usize>() {
+                Ok(id) if id > 0 => Ok(Value::Id(id - 1)),
+                _ => Err(format!("'{}' is not a task ID.", raw)),
+            },
+            ValueType::Date => parse_date(raw).map(Value::Date),
+            ValueType::Time => Time::parse(raw).map(Value::Time).map_err(|e| e.to_string()),
+            ValueType::Duration => parse_duration(raw).map(Value::Duration).map_err(|e| e.to_string()),
+            ValueType::Priority => match raw.to_lowercase().as_str() {
+                "none" => Ok(Value::Priority(None)),
+                _ => Priority::parse(raw).map(|priority| Value::Priority(Some(priority))),
+            },
+            // Terms on custom attributes are checked against the config by `TaskList::filter_tasks_with_attributes`.
+            ValueType::Filter => match is_attribute_term(raw) {
+                true => Ok(Value::Text(raw.to_string())),
+                false => check_filter(raw).map(|_| Value::Text(raw.to_string())),
+            },
+            ValueType::Tasks => match parse_ids(raw).is_ok() || is_attribute_term(raw) || check_filter(raw).is_ok() {
+                true => Ok(Value::Text(raw.to_string())),
+                false => Err(format!("'{}' is not a task ID, range like 1,3,5-9, or filter.", raw)),
+            },
+            ValueType::Choice(choices) => match choices.contains(&raw) {
+                true => Ok(Value::Text(raw.to_string())),
+                false => Err(format!("Expected one of: {}.", choices.join(", "))),
+            },
+            // Anything else may be an alias from the config, which 'help' checks itself.
+            ValueType::Command => match find_command(raw) {
+                Some(command) => Ok(Value::Text(command.name.to_string())),
+                None => Ok(Value::Text(raw.to_string())),
+            },
+        }
+    }
+}
+
+/// Arguments parsed by `Command::parse`, keyed by `Arg::name`.
+#[derive(Debug, Default)]
+pub(crate) struct Matches {
+    pub(crate) help: bool,
+    values: HashMap<&'static str, Value>,
+}
+
+impl Matches {
+    pub(crate) fn is_set(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+
+    pub(crate) fn text(&self, name: &str) -> Option<String> {
+        match self.values.get(name) {
+            Some(Value::Text(text)) => Some(text.clone()),
+            _ => None,
+        }
+    }
+
+    /// Every text value of an argument that can be given more than once.
+    pub(crate) fn texts(&self, name: &str) -> Vec<String> {
+        match self.values.get(name) {
+            Some(Value::List(values)) => values.iter().filter_map(|value| match value {
+                Value::Text(text) => Some(text.clone()),
+                _ => None,
+            }).collect(),
+            Some(Value::Text(text)) => vec![text.clone()],
+            _ => Vec::new(),
+        }
+    }
+
+    pub(crate) fn number(&self, name: &str) -> Option<u32> {
+        match self.values.get(name) {
+            Some(Value::Number(number)) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn id(&self, name: &str) -> Option<usize> {
+        match self.values.get(name) {
+            Some(Value::Id(id)) => Some(*id),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn date(&self, name: &str) -> Option<Date> {
+        match self.values.get(name) {
+            Some(Value::Date(date)) => Some(date.clone()),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn time(&self, name: &str) -> Option<Time> {
+        match self.values.get(name) {
+            Some(Value::Time(time)) => Some(time.clone()),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn duration(&self, name: &str) -> Option<Duration> {
+        match self.values.get(name) {
+            Some(Value::Duration(duration)) => Some(*duration),
+            _ => None,
+        }
+    }
+
+    /// `Some(None)` when the priority was explicitly set to none.
+    pub(crate) fn priority(&self, name: &str) -> Option<Option<Priority>> {
+        match self.values.get(name) {
+            Some(Value::Priority(priority)) => Some(*priority),
+            _ => None,
+        }
+    }
+}
+
+/// The 'taskninja help' overview, listing every command.
+pub(crate) fn overview(commands: &[Command]) -> String {
+    let mut help = String::from("TaskNinja: A command line task manager.\nUsage: taskninja [options] [operation] [arguments]\n\nOperations:\n");
+
+    for command in commands {
+        let names = std::iter::once(command.name).chain(command.aliases.iter().copied()).collect::<Vec<&str>>().join(", ");
+        help.push_str(&format!("    {:<16}{}\n", names, command.summary));
+        if command.name == "help" {
+            help.push_str(&format!("    {:<16} - e.g. 'taskninja help' or 'taskninja help add'\n", ""));
+        }
+    }
+
+    help.push_str("\nOptions:\n");
+    for (option, description) in GLOBAL_OPTIONS {
+        help.push_str(&format!("    {:<20}{}\n", option, description));
+    }
+
+    help
+}
+
+/// Removes a global option given before the command, as `--name VALUE` or `--name=VALUE`, and returns its value.
+pub(crate) fn take_global_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, String> {
+    let mut index = 0;
+    while index < args.len() && args[index].starts_with("--") {
+        if args[index] == name {
+            if index + 1 >= args.len() {
+                return Err(format!("Missing value for '{}'.", name));
+            }
+            let value = args.remove(index + 1);
+            args.remove(index);
+            return Ok(Some(value));
+        }
+        if let Some(value) = args[index].strip_prefix(name).and_then(|rest| rest.strip_prefix('=')) {
+            let value = value.to_string();
+            args.remove(index);
+            return Ok(Some(value));
+        }
+        index += if args[index].contains('=') { 1 } else { 2 };
+    }
+
+    Ok(None)
+}
+
+pub(crate) fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -1,11 +1,20 @@
 use crate::{Config, save_tasks, TaskList};
+use std::collections::BTreeMap;
 use std::fmt;
+use std::io::{self, Write};
 use std::error::Error as StdError;
+use std::path::Path;
 use indoc::{indoc};
-use crate::Date;
-use crate::dates::DateTimeError;
-use crate::Time;
-use crate::utils::read_tasks;
+use crate::cli::{self, Arg, Command, Matches, ValueType};
+use crate::tasks::{format_timestamp, Priority, Status, Task, FILTER_NAMES, SORT_KEYS, STATUS_NAMES};
+use crate::dates::format_duration;
+use crate::filter::parse_ids;
+use crate::config::DEFAULT_LIST;
+use crate::utils::{read_archive, read_tasks, save_archive, SaveError};
+use crate::attributes::check_attributes;
+use crate::search::{self, Matcher, SEARCH_FIELDS};
+use crate::shell::{history_path, split_line};
+use crate::{attributes, completions, daemon, editor, form, hooks, report, shell, tui};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum CommandError {
@@ -13,7 +22,14 @@ pub enum CommandError {
     InvalidHelpOperation(String),
     MissingRequiredArgument(String, String),
     InvalidArgument(String, String),
+    InvalidValue(String, String, String),
     TaskNotFound(String),
+    TerminalError(String),
+    EditorError(String),
+    ConfigError(String),
+    HookError(String),
+    ArchiveError(String),
+    Cancelled(String),
 }
 
 impl fmt::Display for CommandError {
@@ -23,7 +39,14 @@ impl fmt::Display for CommandError {
             CommandError::InvalidHelpOperation(operation) => write!(f, "Invalid help operation. Argument '{}' not found. Run 'taskninja help' for more info.", operation),
             CommandError::MissingRequiredArgument(operation, argument) => write!(f, "Missing required argument '{}' for operation '{}'. Run 'taskninja help {operation}' for more info.", argument, operation),
             CommandError::InvalidArgument(operation, argument) => write!(f, "Invalid argument '{}' for operation '{}'. Run 'taskninja help {operation}' for more info.", argument, operation),
+            CommandError::InvalidValue(operation, argument, reason) => write!(f, "Invalid value for '{}' in operation '{}'. {} Run 'taskninja help {operation}' for more info.", argument, operation, reason),
             CommandError::TaskNotFound(task) => write!(f, "Task '{}' not found.", task),
+            CommandError::TerminalError(error) => write!(f, "Terminal error: {}", error),
+            CommandError::EditorError(error) => write!(f, "Editor error: {}", error),
+            CommandError::ConfigError(error) => write!(f, "Config error: {}", error),
+            CommandError::HookError(error) => write!(f, "Hook error: {}", error),
+            CommandError::ArchiveError(error) => write!(f, "Archive error: {} No tasks were archived.", error),
+            CommandError::Cancelled(operation) => write!(f, "{} cancelled. No tasks were changed.", operation),
         }
     }
 }
@@ -35,483 +58,1446 @@ impl StdError for CommandError {
             CommandError::InvalidHelpOperation(_) => "Invalid help operation.",
             CommandError::MissingRequiredArgument(_, _) => "Missing required argument.",
             CommandError::InvalidArgument(_, _) => "Invalid argument.",
+            CommandError::InvalidValue(_, _, _) => "Invalid value.",
             CommandError::TaskNotFound(_) => "Task not found.",
+            CommandError::TerminalError(_) => "Terminal error.",
+            CommandError::EditorError(_) => "Editor error.",
+            CommandError::ConfigError(_) => "Config error.",
+            CommandError::HookError(_) => "Hook error.",
+            CommandError::ArchiveError(_) => "Archive error.",
+            CommandError::Cancelled(_) => "Cancelled.",
         }
     }
 }
 
+/// Commands that change tasks in place. The on-modify and on-complete hooks run on every task they change.
+/// 'move', 'transfer' and 'trash restore' only change where tasks are, so no hooks run for them.
+const MODIFYING: [&str; 9] = ["complete", "incomplete", "start", "block", "wait", "cancel", "edit", "annotate", "track"];
+
+const ALL: Arg = Arg::switch("all", &["all", "-a", "--all"], "Apply to every task.");
+const ID: Arg = Arg::value("id", &[], ValueType::Id, "ID of the task.").positional();
+const TASKS: Arg = Arg::value("tasks", &[], ValueType::Tasks, "IDs and ranges like 1,3,5-9, and filters like 'tag:sprint12'.").positional().multiple();
+const YES: Arg = Arg::switch("yes", &["-y", "--yes"], "Don't ask before changing many tasks.");
+
+/// Help on choosing tasks for the commands that take `TASKS`. A macro so other notes can `concat!` it.
+macro_rules! tasks_notes {
+    () => {
+        indoc! {"
+            Tasks are chosen by IDs and ranges, filters (see 'taskninja help list'), or both, in which case the
+            filters narrow down the IDs. Commands affecting more than 'confirm_threshold' tasks (3 by default)
+            list them and ask before going ahead.
+        "}
+    };
+}
+
+const TASKS_NOTES: &str = tasks_notes!();
+
+/// Every command TaskNinja understands. Parsing, help and shell completions are all generated from this table.
+pub(crate) const COMMANDS: [Command; 28] = [
+    Command {
+        name: "help",
+        aliases: &["h"],
+        summary: "Display this help message or get more detailed help on an operation.",
+        args: &[
+            Arg::value("operation", &[], ValueType::Command, "Operation to display detailed help about.").positional(),
+        ],
+        examples: &["taskninja help", "taskninja help add"],
+        notes: "",
+        handler: help,
+    },
+    Command {
+        name: "add",
+        aliases: &["a"],
+        summary: "Add a new task.",
+        args: &[
+            Arg::value("title", &["-t", "--title"], ValueType::Text, "Title of the task.").positional().required(),
+            Arg::value("description", &["-d", "--description"], ValueType::Text, "Description of the task."),
+            Arg::value("date", &["due", "-D", "--date"], ValueType::Date, "Due date of the task. (YYYY-MM-DD, YYYY-Month_name-DD, tomorrow or +3d)"),
+            Arg::value("time", &["at", "-T", "--time"], ValueType::Time, "Due time of the task. (HH:MM)"),
+            Arg::value("scheduled", &["--scheduled"], ValueType::Date, "When to start working on the task."),
+            Arg::value("wait", &["--wait"], ValueType::Date, "Hide the task from 'list' until this date."),
+            Arg::value("until", &["--until"], ValueType::Date, "Cancel the task automatically after this date."),
+            Arg::switch("flag", &["flag", "-f", "--flag"], "Mark the task as important."),
+            Arg::value("priority", &["-p", "--priority"], ValueType::Priority, "Priority of the task. (high, medium or low)"),
+            Arg::value("project", &["-P", "--project"], ValueType::Text, "Project the task belongs to. Use dots for subprojects, e.g. 'work.client'."),
+            Arg::value("estimate", &["-E", "--estimate"], ValueType::Duration, "Expected effort. (e.g. 1h30m, 2h or 45m)"),
+            Arg::value("remind", &["-r", "--remind"], ValueType::Duration, "Send a reminder this long before the task is due. (e.g. 15m or 1h)"),
+            Arg::value("tag", &["-g", "--tag"], ValueType::Text, "Tag the task. Can be given more than once.").multiple(),
+            Arg::value("attribute", &[], ValueType::Text, "A context as @name, and custom attributes as name:value declared under 'attributes' in the config.").positional().multiple(),
+        ],
+        examples: &[
+            "taskninja add -t 'Get into Cornell.' -D 2022-09-12 -T 12:06 -f",
+            "taskninja add -t 'Ask out for Last Hurrah.' -D 2022-September-12",
+            "taskninja add -t 'Blah blah blah.' -d 'More nonsense' -f -p high",
+            "taskninja add 'Go on a run' due 2022-09-12 at 12:06 flag",
+            "taskninja add 'Write the report' -P work.client -E 2h -g billing -g writing",
+            "taskninja add 'Renew passport' due 2025-03-01 --scheduled 2025-02-01 --wait 2025-01-15",
+            "taskninja add 'Fix the login page' customer:acme severity:high",
+            "taskninja add 'Call the plumber' @phone",
+            "taskninja add 'Team meeting' due today at 14:00 --remind 15m",
+        ],
+        notes: "",
+        handler: add,
+    },
+    Command {
+        name: "delete",
+        aliases: &["del", "d"],
+        summary: "Move tasks to the trash.",
+        args: &[TASKS, ALL, YES],
+        examples: &["taskninja delete 1", "taskninja delete 2,4-6", "taskninja delete cancelled", "taskninja delete all"],
+        notes: concat!("Deleted tasks can be brought back with 'taskninja trash restore' until the trash is emptied.\n\n", tasks_notes!()),
+        handler: delete,
+    },
+    Command {
+        name: "complete",
+        aliases: &["c"],
+        summary: "Mark tasks as done.",
+        args: &[TASKS, ALL, YES],
+        examples: &["taskninja complete 1", "taskninja complete 1,3,5-9", "taskninja complete tag:sprint12 due.before:today -y"],
+        notes: TASKS_NOTES,
+        handler: complete,
+    },
+    Command {
+        name: "incomplete",
+        aliases: &["i"],
+        summary: "Mark tasks as to do again.",
+        args: &[TASKS, ALL, YES],
+        examples: &["taskninja incomplete 1", "taskninja incomplete 2-4"],
+        notes: TASKS_NOTES,
+        handler: incomplete,
+    },
+    Command {
+        name: "start",
+        aliases: &[],
+        summary: "Mark tasks as in progress.",
+        args: &[TASKS, ALL, YES],
+        examples: &["taskninja start 1", "taskninja start 2-4"],
+        notes: TASKS_NOTES,
+        handler: start,
+    },
+    Command {
+        name: "block",
+        aliases: &[],
+        summary: "Mark tasks as blocked.",
+        args: &[TASKS, ALL, YES],
+        examples: &["taskninja block 1", "taskninja block 2-4"],
+        notes: TASKS_NOTES,
+        handler: block,
+    },
+    Command {
+        name: "wait",
+        aliases: &[],
+        summary: "Mark tasks as waiting on someone else.",
+        args: &[TASKS, ALL, YES],
+        examples: &["taskninja wait 1", "taskninja wait 2-4"],
+        notes: TASKS_NOTES,
+        handler: wait,
+    },
+    Command {
+        name: "cancel",
+        aliases: &[],
+        summary: "Mark tasks as cancelled.",
+        args: &[TASKS, ALL, YES],
+        examples: &["taskninja cancel 1", "taskninja cancel 2-4"],
+        notes: TASKS_NOTES,
+        handler: cancel,
+    },
+    Command {
+        name: "list",
+        aliases: &["l"],
+        summary: "List all tasks.",
+        args: &[
+            Arg::switch("all", &["a", "all", "-a", "--all"], "List every task, ignoring other options."),
+            Arg::switch("complete", &["-c", "--complete"], "List only done tasks."),
+            Arg::switch("incomplete", &["-i", "--incomplete"], "List only tasks that are neither done nor cancelled."),
+            Arg::value("status", &["-S", "--status"], ValueType::Choice(&STATUS_NAMES), "List only tasks with this status."),
+            Arg::switch("flagged", &["-f", "--flagged"], "List only flagged tasks."),
+            Arg::switch("unflagged", &["-u", "--unflagged"], "List only unflagged tasks."),
+            Arg::switch("due_today", &["-t", "--today"], "List only tasks due today."),
+            Arg::value("priority", &["-p", "--priority"], ValueType::Priority, "List only tasks with this priority. (high, medium, low or none)"),
+            Arg::value("sort", &["-s", "--sort"], ValueType::Text, "Order tasks by position, priority, due date, when they were created, modified or completed, or a custom attribute."),
+            Arg::switch("all_lists", &["-L", "--all-lists"], "List the matching tasks of every task list."),
+            Arg::value("filter", &[], ValueType::Filter, "Filter names, date terms or attribute terms, e.g. 'completed:last_week'.").positional().multiple(),
+        ],
+        examples: &["taskninja list", "taskninja list -c", "taskninja list --all-lists -t", "taskninja list --today", "taskninja list -i -p high", "taskninja list -S in_progress", "taskninja list --sort priority", "taskninja list completed:last_week", "taskninja list severity:high --sort customer"],
+        notes: indoc! {"
+            Date terms:
+                FIELD:WHEN          Tasks whose date falls on or within WHEN.
+                FIELD.before:WHEN   Tasks whose date is before WHEN.
+                FIELD.after:WHEN    Tasks whose date is after WHEN.
+
+                FIELD is created, modified, completed, due, scheduled, wait or until. WHEN is a date
+                (YYYY-MM-DD), an offset from today (-3d, 2w), or one of today, yesterday, tomorrow,
+                this_week, last_week, next_week, this_month and last_month.
+                 - e.g. 'created.after:-7d' or 'due.before:tomorrow'
+
+            Attribute terms, for custom attributes declared in the config:
+                NAME:VALUE          Tasks with this value.
+                NAME:               Tasks without the attribute.
+                NAME.above:VALUE    Tasks with a greater value. Not for string attributes.
+                NAME.below:VALUE    Tasks with a smaller value. Not for string attributes.
+                 - e.g. 'customer:acme' or 'severity.above:low'
+
+            Tasks with a wait date in the future are hidden unless 'all' or 'hidden' is given.
+            'taskninja report waiting' lists them.
+        "},
+        handler: list,
+    },
+    Command {
+        name: "move",
+        aliases: &["mv", "m"],
+        summary: "Move a task to another position in the list.",
+        args: &[
+            ID.required(),
+            Arg::value("position", &[], ValueType::Number, "New position of the task. (1 or higher)").positional().required(),
+        ],
+        examples: &["taskninja move 4 1"],
+        notes: "",
+        handler: move_task,
+    },
+    Command {
+        name: "next",
+        aliases: &["n"],
+        summary: "Show the most urgent open tasks.",
+        args: &[
+            Arg::value("count", &["-n", "--count"], ValueType::Number, "Number of tasks to show. (Default 5)").positional(),
+        ],
+        examples: &["taskninja next", "taskninja next 10"],
+        notes: indoc! {"
+            Urgency combines how soon a task is due, whether it's overdue, flagged, its priority, age,
+            tags and status. The weights can be changed under 'urgency' in the config file.
+        "},
+        handler: next,
+    },
+    Command {
+        name: "search",
+        aliases: &["s"],
+        summary: "Search for tasks.",
+        args: &[
+            Arg::value("query", &[], ValueType::Text, "Text to search for.").positional().required(),
+            Arg::switch("exact", &["-e", "--exact"], "Match case exactly."),
+            Arg::switch("regex", &["-r", "--regex"], "Read the query as a regular expression."),
+            Arg::switch("fuzzy", &["-f", "--fuzzy"], "Find the query's characters in order, with anything in between."),
+            Arg::value("in", &["-i", "--in"], ValueType::Choice(&SEARCH_FIELDS), "Only search this field. (title, description, project, tags or annotations)").multiple(),
+            Arg::switch("archive", &["-A", "--archive"], "Search archived tasks too."),
+        ],
+        examples: &[
+            "taskninja search 'shopping'",
+            "taskninja search 'Go shopping.' -e",
+            "taskninja search 'invoice' --archive",
+            "taskninja search 'grcs' --fuzzy",
+            "taskninja search '^fix (login|signup)' --regex --in title",
+            "taskninja search urgent --in tags --in project",
+        ],
+        notes: indoc! {"
+            Titles, descriptions, projects, tags and annotations are searched unless '--in' picks fields.
+            Results are ranked, best first: earlier and closer matches rank higher, and matches in the title
+            count more than in the project or tags, which count more than elsewhere.
+        "},
+        handler: search,
+    },
+    Command {
+        name: "edit",
+        aliases: &["e"],
+        summary: "Edit tasks.",
+        args: &[
+            Arg::value("tasks", &[], ValueType::Tasks, "ID, IDs and ranges like 1,3,5-9, or a filter like 'tag:sprint12'.").positional().required(),
+            Arg::value("where", &["-w", "--where"], ValueType::Filter, "Only edit tasks matching this filter. Can be given more than once.").multiple(),
+            YES,
+            Arg::value("title", &["-t", "--title"], ValueType::Text, "Title of the task."),
+            Arg::value("description", &["-d", "--description"], ValueType::Text, "Description of the task."),
+            Arg::value("date", &["due", "-D", "--date"], ValueType::Date, "Due date of the task. (YYYY-MM-DD, YYYY-Month_name-DD, tomorrow or +3d)"),
+            Arg::value("time", &["at", "-T", "--time"], ValueType::Time, "Due time of the task. (HH:MM)"),
+            Arg::value("scheduled", &["--scheduled"], ValueType::Date, "When to start working on the task."),
+            Arg::value("wait", &["--wait"], ValueType::Date, "Hide the task from 'list' until this date."),
+            Arg::value("until", &["--until"], ValueType::Date, "Cancel the task automatically after this date."),
+            Arg::switch("flag", &["flag", "-f", "--flag"], "Mark the task as important."),
+            Arg::value("priority", &["-p", "--priority"], ValueType::Priority, "Priority of the task. (high, medium, low or none)"),
+            Arg::value("project", &["-P", "--project"], ValueType::Text, "Project the task belongs to. Use dots for subprojects, e.g. 'work.client'."),
+            Arg::value("estimate", &["-E", "--estimate"], ValueType::Duration, "Expected effort. (e.g. 1h30m, 2h or 45m)"),
+            Arg::value("remind", &["-r", "--remind"], ValueType::Duration, "Send a reminder this long before the task is due. (e.g. 15m or 1h)"),
+            Arg::value("tag", &["-g", "--tag"], ValueType::Text, "Tag the task. Can be given more than once.").multiple(),
+            Arg::value("attribute", &[], ValueType::Text, "A context as @name, and custom attributes as name:value declared under 'attributes' in the config.").positional().multiple(),
+            Arg::value("untag", &["-G", "--untag"], ValueType::Text, "Remove a tag from the task. Can be given more than once.").multiple(),
+            Arg::switch("notes", &["-n", "--notes"], "Open the task's Markdown notes in $EDITOR."),
+        ],
+        examples: &[
+            "taskninja edit 1",
+            "taskninja edit 1 -t 'Go shopping.' -D 2022-09-12 -T 12:06 -f",
+            "taskninja edit 1 -D 2022-September-12",
+            "taskninja edit 1 -t 'Blah blah blah.'",
+            "taskninja edit 1 -p none",
+            "taskninja edit 1 -E 3h",
+            "taskninja edit 1 -g urgent -G someday",
+            "taskninja edit 1 --notes",
+            "taskninja edit 1 severity:low customer:",
+            "taskninja edit 1 @office",
+            "taskninja edit 1 @none",
+            "taskninja edit 1 due 2022-09-12 at 12:06 flag",
+            "taskninja edit 3-7 -P work.client",
+            "taskninja edit tag:sprint12 --where incomplete -g sprint13 -G sprint12",
+        ],
+        notes: indoc! {"
+            Without any options, the whole task opens in $EDITOR.
+            Set the project or description to '' to remove it.
+            Several tasks can be edited at once, as long as options are given. Use --where to filter
+            them further. Editing more than 'confirm_threshold' tasks (3 by default) lists them and asks
+            before going ahead.
+        "},
+        handler: edit,
+    },
+    Command {
+        name: "annotate",
+        aliases: &[],
+        summary: "Add a timestamped note to a task, or remove one.",
+        args: &[
+            ID.required(),
+            Arg::value("text", &[], ValueType::Text, "Text of the annotation.").positional(),
+            Arg::value("remove", &["-r", "--remove"], ValueType::Number, "Remove the annotation with this number instead."),
+        ],
+        examples: &["taskninja annotate 1 'Called the client, waiting on a reply.'", "taskninja annotate 1 --remove 2"],
+        notes: "",
+        handler: annotate,
+    },
+    Command {
+        name: "track",
+        aliases: &[],
+        summary: "Track time spent on a task.",
+        args: &[
+            Arg::value("action", &[], ValueType::Choice(&["start", "stop", "status", "add"]), "What to do. (start, stop, status or add)").positional().required(),
+            ID,
+            Arg::value("duration", &[], ValueType::Duration, "Time to add with 'add'. (e.g. 1h30m, 2h or 45m)").positional(),
+        ],
+        examples: &["taskninja track start 1", "taskninja track stop", "taskninja track status", "taskninja track add 1 1h30m"],
+        notes: indoc! {"
+            Only one timer runs at a time. Starting a timer stops the one that was running.
+        "},
+        handler: track,
+    },
+    Command {
+        name: "report",
+        aliases: &[],
+        summary: "Print a report about your tasks.",
+        args: &[
+            Arg::value("report", &[], ValueType::Text, "Report to print. (effort, waiting or a saved report)").positional().required(),
+        ],
+        examples: &["taskninja report effort", "taskninja report waiting", "taskninja report overdue"],
+        notes: indoc! {"
+            Reports:
+                effort              Estimated against tracked time per task, project and week completed,
+                                    estimation accuracy and the estimated work left on tasks due this week.
+                waiting             Open tasks hidden by a wait date, soonest first.
+
+            Saved reports are declared under 'reports' in the config:
+                filter              Filter terms, as 'list' takes them.
+                sort                Sort key, as 'list --sort' takes it. (Optional)
+                columns             Any of id, title, status, priority, due, scheduled, project, tags, context,
+                                    urgency, estimate, tracked, created, modified, completed and custom
+                                    attributes. (Default: id, title, status, due)
+                format              table, plain, csv or json. (Default: table)
+        "},
+        handler: report,
+    },
+    Command {
+        name: "context",
+        aliases: &[],
+        summary: "Limit 'list' and 'search' to the tasks of a context.",
+        args: &[
+            Arg::value("action", &[], ValueType::Choice(&["set", "clear", "show", "list", "define"]), "What to do. (set, clear, show, list or define)").positional(),
+            Arg::value("name", &[], ValueType::Text, "Name of the context.").positional(),
+            Arg::value("filter", &[], ValueType::Filter, "Filters the context applies, with 'define'. See 'taskninja help list'.").positional().multiple(),
+        ],
+        examples: &[
+            "taskninja context define home @home incomplete",
+            "taskninja context define work @office project:work",
+            "taskninja context set home",
+            "taskninja context clear",
+        ],
+        notes: indoc! {"
+            Contexts are stored under 'contexts' in the config as named filter expressions. While one is set,
+            'list' and 'search' only show tasks matching its filters, until 'context clear'. 'list all'
+            ignores the context. 'context define NAME' without filters removes a context.
+        "},
+        handler: context,
+    },
+    Command {
+        name: "use",
+        aliases: &[],
+        summary: "Switch to another task list, or show the lists.",
+        args: &[
+            Arg::value("name", &[], ValueType::Text, "Name of the list. 'default' is the list in 'data_file'.").positional(),
+        ],
+        examples: &["taskninja use", "taskninja use team-backend", "taskninja use default", "taskninja --list personal list"],
+        notes: indoc! {"
+            Each list keeps its tasks in a data file of its own. Using a list that doesn't exist yet creates it,
+            stored in 'lists/NAME.json' next to 'data_file'. Lists are recorded under 'lists' in the config, where
+            their files can be changed.
+
+            '--list NAME' before any command runs just that command on another list.
+        "},
+        handler: use_list,
+    },
+    Command {
+        name: "transfer",
+        aliases: &[],
+        summary: "Move or copy tasks to another task list.",
+        args: &[
+            TASKS,
+            Arg::value("to", &["-t", "--to"], ValueType::Text, "Name of the list to move the tasks to."),
+            Arg::switch("copy", &["-c", "--copy"], "Copy the tasks, keeping them in this list too."),
+            ALL,
+            YES,
+        ],
+        examples: &["taskninja transfer 3 --to personal", "taskninja transfer 2,4-6 --to team-backend --copy", "taskninja transfer project:infra --to team-backend"],
+        notes: TASKS_NOTES,
+        handler: transfer,
+    },
+    Command {
+        name: "trash",
+        aliases: &[],
+        summary: "List, restore or empty deleted tasks.",
+        args: &[
+            Arg::value("action", &[], ValueType::Choice(&["list", "restore", "empty"]), "What to do. (list, restore or empty)").positional(),
+            Arg::value("number", &[], ValueType::Number, "Number of the trashed task to restore, as 'trash list' shows it.").positional(),
+        ],
+        examples: &["taskninja trash", "taskninja trash restore 2", "taskninja trash empty"],
+        notes: indoc! {"
+            Restored tasks go to the end of the list. Emptying the trash deletes its tasks for good.
+        "},
+        handler: trash,
+    },
+    Command {
+        name: "archive",
+        aliases: &[],
+        summary: "Move old done and cancelled tasks to the archive.",
+        args: &[
+            Arg::value("days", &[], ValueType::Number, "Archive tasks closed more than this many days ago. (Default: 30)").positional(),
+        ],
+        examples: &["taskninja archive", "taskninja archive 7"],
+        notes: indoc! {"
+            Archived tasks are kept in 'archive_file' (archive.json in the data directory by default) and found by
+            'search --archive'.
+        "},
+        handler: archive,
+    },
+    Command {
+        name: "daemon",
+        aliases: &[],
+        summary: "Send reminders for tasks as they come due.",
+        args: &[],
+        examples: &["taskninja daemon", "taskninja daemon > reminders.log &"],
+        notes: indoc! {"
+            The daemon watches the data file and sends a reminder when a task is due, or '--remind' before.
+            Tasks due on a date without a time are reminded about at 'all_day_time'. Where reminders go is set
+            under 'reminders' in the config:
+                command             Shell command run for each reminder, with the message as $1 and the
+                                    task in TASKNINJA_ID, TASKNINJA_TITLE and TASKNINJA_DUE.
+                                     - e.g. 'notify-send TaskNinja \"$1\"'
+                log                 File to append reminders to.
+                socket              Unix socket to write reminders to.
+            Without any of them, reminders are printed to stdout.
+        "},
+        handler: daemon,
+    },
+    Command {
+        name: "config",
+        aliases: &[],
+        summary: "View and change settings.",
+        args: &[
+            Arg::value("action", &[], ValueType::Choice(&["list", "get", "set", "reset"]), "What to do. (list, get, set or reset)").positional(),
+            Arg::value("key", &[], ValueType::Text, "Setting to get, set or reset, e.g. 'time_24_hour' or 'urgency.due'.").positional(),
+            Arg::value("value", &[], ValueType::Text, "New value, with 'set'.").positional().multiple(),
+        ],
+        examples: &[
+            "taskninja config list",
+            "taskninja config get urgency",
+            "taskninja config set time_24_hour true",
+            "taskninja config set error_color 'bold red'",
+            "taskninja config set contexts.home @home incomplete",
+            "taskninja config reset urgency.due",
+        ],
+        notes: indoc! {"
+            Values are read as the type of the setting: true or false, numbers, or text. 'none' clears optional
+            settings. Settings holding groups or lists of values, like 'attributes.NAME', take JSON.
+
+            'config reset KEY' puts a setting back to its default and removes user-defined entries such as
+            'contexts.NAME'. 'config reset' resets every setting except 'data_file', 'archive_file', 'lists'
+            and 'list'.
+        "},
+        handler: configure,
+    },
+    Command {
+        name: "doctor",
+        aliases: &[],
+        summary: "Show which files TaskNinja uses and check the setup.",
+        args: &[],
+        examples: &["taskninja doctor", "taskninja --list work doctor"],
+        notes: indoc! {"
+            The config file is taken from '--config', then TASKNINJA_CONFIG, then config.json in
+            $XDG_CONFIG_HOME/taskninja (~/.config/taskninja). Task lists and the archive default to
+            $XDG_DATA_HOME/taskninja (~/.local/share/taskninja). '--data-file' or TASKNINJA_DATA replace
+            the data file of the current list.
+
+            Setups still keeping data/config.json in the working directory keep working until a config
+            exists in the XDG location.
+        "},
+        handler: doctor,
+    },
+    Command {
+        name: "tui",
+        aliases: &["t"],
+        summary: "Open the interactive task view.",
+        args: &[],
+        examples: &["taskninja tui"],
+        notes: indoc! {"
+            Keys:
+                j, k, Up, Down      Move the selection.
+                PgUp, PgDn, g, G    Scroll by a page, jump to the first or last task.
+                space, c            Toggle done.
+                s, b, w, x          Toggle in progress, blocked, waiting or cancelled.
+                f                   Toggle flag.
+                t                   Edit the title.
+                d                   Edit the description.
+                D                   Edit the due date and time. (e.g. 2024-05-01 09:00, tomorrow or +3d)
+                /                   Filter tasks. (e.g. incomplete high_priority due.before:next_week)
+                q, Esc              Quit.
+        "},
+        handler: tui,
+    },
+    Command {
+        name: "shell",
+        aliases: &[],
+        summary: "Open an interactive prompt that keeps tasks loaded.",
+        args: &[
+            Arg::switch("no_autosave", &["--no-autosave"], "Only write tasks when 'save' is run."),
+        ],
+        examples: &["taskninja shell", "taskninja shell --no-autosave"],
+        notes: indoc! {"
+            Commands inside the shell:
+                Any operation       Same grammar as the command line, without 'taskninja'.
+                                     - e.g. 'add 'Go on a run' due 2022-09-12' or 'complete 3'
+                save                Write tasks to the data file.
+                autosave on|off     Save after every change, or only on 'save'.
+                exit, quit          Leave the shell. Use 'exit!' to discard unsaved changes.
+
+            Tab completes operations, flags, task IDs and tags. History is kept between sessions.
+        "},
+        handler: shell,
+    },
+    Command {
+        name: "completions",
+        aliases: &[],
+        summary: "Print a shell completion script.",
+        args: &[
+            Arg::value("shell", &[], ValueType::Choice(&["bash", "zsh", "fish"]), "Shell to print the completion script for. (bash, zsh or fish)").positional(),
+            Arg::switch("tasks", &["--tasks"], "Print task IDs and titles. Used by the completion scripts."),
+        ],
+        examples: &[
+            "taskninja completions bash > ~/.local/share/bash-completion/completions/taskninja",
+            "taskninja completions zsh > ~/.zfunc/_taskninja",
+            "taskninja completions fish > ~/.config/fish/completions/taskninja.fish",
+        ],
+        notes: "",
+        handler: completions,
+    },
+];
+
+/// Looks up a command by its name or one of its aliases.
+pub(crate) fn find_command(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|command| command.matches_name(&name.to_lowercase()))
+}
+
 pub struct Response;
 
 impl Response {
-    fn help(help_token: &str) -> Result<String, CommandError> {
+    pub(crate) fn help(help_token: &str) -> Result<String, CommandError> {
         match help_token.to_lowercase().as_str() {
-            "help" => Ok(
-                indoc! {"
-                    TaskNinja: A command line task manager.
-                    Usage: taskninja [operation] [arguments]
-
-                    Operations:
-                        help, h         Display this help message or get more detailed help on an operation.
-                                         - e.g. 'taskninja help' or 'taskninja help add'
-                        add, a          Add a new task.
-                        delete, d       Delete a task.
-                        complete, c     Mark a task as complete.
-                        incomplete, i   Mark a task as incomplete.
-                        list, l         List all tasks.
-                        search, s       Search for tasks.
-                        edit, e         Edit a task.
-                "}.to_string()
-            ),
-            "add" => Ok(
-                indoc! {"
-                    taskninja add: Add a new task.
-                    Usage: taskninja add [title] [options]
-
-                    Arguments for 'add':
-                        help, -h, --help    Display detailed help about the add operation.
-                        -t, --title         Title of the task. (Required)
-
-                        -d, --description   Description of the task. (Optional)
-                                             - Can be set without the -d or --description flag.
-                        due, -D, --date     Due date of the task. (YYYY-MM-DD or YYYY-Month_name-DD) (Optional)
-                        at, -T, --time      Due time of the task. (HH:MM) (Optional)
-                        flag, -f, --flag    Mark the task as important. (Optional)
-                        -p, --priority      Set priority of the task. (1 or higher) (Optional)
-
-                    Examples:
-                        taskninja add -t 'Get into Cornell.' -D 2022-09-12 -T 12:06 -r -f
-                        taskninja add -t 'Ask out for Last Hurrah.' -D 2022-September-12
-                        taskninja add -t 'Blah blah blah.' -d 'More nonsense' -f -p 2
-                        taskninja add 'Go on a run' due 2022-09-12 at 12:06 flag
-                "}.to_string()
-            ),
-            "delete" => Ok(
-                indoc! {"
-                    taskninja delete: Delete a task.
-                    Usage: taskninja delete [ID]
-
-                    Arguments for 'delete':
-                        -h, --help          Display detailed help about the delete operation.
-
-                    Examples:
-                        taskninja delete 1
-                "}.to_string()
-            ),
-            "complete" => Ok(
-                indoc! {"
-                    taskninja complete: Mark a task as complete.
-                    Usage: taskninja complete [ID]
-
-                    Arguments for 'complete':
-                        -h, --help          Display detailed help about the complete operation.
-
-                    Examples:
-                        taskninja complete 1
-                "}.to_string()
-            ),
-            "incomplete" => Ok(
-                indoc! {"
-                    taskninja incomplete: Mark a task as incomplete.
-                    Usage: taskninja incomplete [ID]
-
-                    Arguments for 'incomplete':
-                        -h, --help          Display detailed help about the incomplete operation.
-
-                    Examples:
-                        taskninja incomplete 1
-                "}.to_string()
-            ),
-            "list" => Ok(
-                indoc! {"
-                    taskninja list: List all tasks.
-                    Usage: taskninja list [options]
-
-                    Arguments for 'list':
-                        -h, --help          Display detailed help about the list operation.
-                        -c, --complete      List only complete tasks. (Optional)
-                        -i, --incomplete    List only incomplete tasks. (Optional)
-                        -f, --flagged       List only flagged tasks. (Optional)
-                        -u, --unflagged     List only unflagged tasks. (Optional)
-                        -t, --today         List only tasks due today. (Optional)
-
-                    Examples:
-                        taskninja list
-                        taskninja list -c
-                        taskninja list --today
-                "}.to_string()
-            ),
-            "search" => Ok(
-                indoc! {"
-                    taskninja search: Search for tasks.
-                    Usage: taskninja search [query] [options]
-
-                    Arguments for 'search':
-                        -h, --help          Display detailed help about the search operation.
-                        -e, --exact         Search for an exact match. (Optional)
-
-                    Examples:
-                        taskninja search 'shopping'
-                        taskninja search 'Go shopping.' -e
-                "}.to_string()
-            ),
-            "edit" => Ok(
-                indoc! {"
-                    taskninja edit: Edit a task.
-                    Usage: taskninja edit [ID] [options]
-
-                    Arguments for 'edit':
-                        -h, --help          Display detailed help about the edit operation.
-                        -t, --title         Title of the task. (Optional)
-                        -d, --description   Description of the task. (Optional)
-                        due, -D, --date     Due date of the task. (YYYY-MM-DD or YYYY-Month_name-DD) (Optional)
-                        at, -T, --time      Due time of the task. (HH:MM) (Optional)
-                        flag, -f, --flag    Mark the task as important. (Optional)
-                        -p, --priority      Set priority of the task. (1 or higher) (Optional)
-
-                    Examples:
-                        taskninja edit 1 -t 'Go shopping.' -D 2022-09-12 -T 12:06 -r -f
-                        taskninja edit 1 -D 2022-September-12
-                        taskninja edit 1 -t 'Blah blah blah.'
-                        taskninja edit 1 due 2022-09-12 at 12:06 flag
-                "}.to_string()
-            ),
-
-            _ => Err(CommandError::InvalidHelpOperation(help_token.to_string())),
+            "help" => Ok(cli::overview(&COMMANDS)),
+            token => match find_command(token) {
+                Some(command) => Ok(command.help()),
+                None => Err(CommandError::InvalidHelpOperation(help_token.to_string())),
+            },
         }
     }
 }
 
-pub(crate) fn command_handler(command: Vec<String>, config: Config) -> Result<String, CommandError> {
-    let mut task_list: TaskList;
+fn help(matches: &Matches, _task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    match matches.text("operation") {
+        Some(operation) if find_command(&operation).is_none() && config.aliases.contains_key(&operation) => {
+            Ok(format!("'{}' is an alias for '{}'.", operation, config.aliases[&operation]))
+        }
+        Some(operation) => Response::help(&operation),
+        None => {
+            let mut help = Response::help("help")?;
+            if !config.aliases.is_empty() {
+                help.push_str("\nAliases:\n");
+                for (name, expansion) in &config.aliases {
+                    help.push_str(&format!("    {:<16}{}\n", name, expansion));
+                }
+            }
+            Ok(help)
+        }
+    }
+}
 
-    match read_tasks(config.clone()) {
-        Ok(tasks) => {
-            task_list = tasks;
+fn required_id(matches: &Matches, operation: &str) -> Result<usize, CommandError> {
+    matches.id("id").ok_or_else(|| CommandError::MissingRequiredArgument(operation.to_string(), "ID".to_string()))
+}
+
+/// Indexes of the tasks a command applies to, from 'all', IDs and ranges, and filters. Filters narrow down
+/// the IDs, or choose from every task when there are none. Asks before going ahead above `confirm_threshold`.
+fn select_tasks(matches: &Matches, task_list: &TaskList, config: &Config, operation: &str) -> Result<Vec<usize>, CommandError> {
+    let mut ids: Option<Vec<usize>> = matches.is_set("all").then(|| (0..task_list.tasks.len()).collect());
+    let mut filters = matches.texts("where");
+
+    for word in matches.texts("tasks") {
+        match parse_ids(&word) {
+            Ok(more) => ids.get_or_insert_with(Vec::new).extend(more),
+            Err(_) => filters.push(word),
         }
-        Err(_) => {
-            task_list = TaskList::new();
+    }
+
+    if ids.is_none() && filters.is_empty() {
+        return Err(CommandError::MissingRequiredArgument(operation.to_string(), "Tasks".to_string()));
+    }
+
+    let mut selected = ids.unwrap_or_else(|| (0..task_list.tasks.len()).collect());
+    selected.sort();
+    selected.dedup();
+    if let Some(missing) = selected.iter().find(|index| **index >= task_list.tasks.len()) {
+        return Err(CommandError::TaskNotFound((missing + 1).to_string()));
+    }
+
+    if !filters.is_empty() {
+        let matching = task_list.filter_indexes_with_attributes(filters.iter().map(|filter| filter.as_str()).collect(), &config.attributes)
+            .map_err(|error| CommandError::InvalidValue(operation.to_string(), "Filter".to_string(), error))?;
+        selected.retain(|index| matching.contains(index));
+    }
+
+    if selected.is_empty() && !matches.is_set("all") {
+        return Err(CommandError::InvalidValue(operation.to_string(), "Tasks".to_string(), format!("No tasks match '{}'.", filters.join(" "))));
+    }
+
+    if selected.len() > config.confirm_threshold && !matches.is_set("yes") {
+        confirm(task_list, &selected, operation)?;
+    }
+
+    Ok(selected)
+}
+
+/// Lists the tasks about to be changed and asks whether to go ahead.
+fn confirm(task_list: &TaskList, selected: &[usize], operation: &str) -> Result<(), CommandError> {
+    println!("{} will change {} tasks:", operation, selected.len());
+    for index in selected {
+        println!("    {}: {}", task_list.tasks[*index].num, task_list.tasks[*index].title);
+    }
+    print!("Continue? (y/N) ");
+    let _ = io::stdout().flush();
+
+    let mut answer = String::new();
+    let _ = io::stdin().read_line(&mut answer);
+    match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => Ok(()),
+        _ => Err(CommandError::Cancelled(operation.to_string())),
+    }
+}
+
+fn add(matches: &Matches, task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    let title = matches.text("title").ok_or_else(|| CommandError::MissingRequiredArgument("Add".to_string(), "Title".to_string()))?;
+    let priority = matches.priority("priority").flatten();
+    let attributes = parse_attributes(matches, &config, "Add")?;
+
+    task_list.new_task(title.clone(), matches.text("description"), matches.date("date").map(Ok), matches.time("time").map(Ok), priority, matches.text("project"), matches.duration("estimate"), matches.texts("tag"), Status::Todo, matches.is_set("flag"));
+    if ["scheduled", "wait", "until"].iter().any(|date| matches.is_set(date)) {
+        task_list.schedule_task(task_list.tasks.len() - 1, matches.date("scheduled").map(Some), matches.date("wait").map(Some), matches.date("until").map(Some))
+            .map_err(CommandError::TaskNotFound)?;
+    }
+    if !attributes.is_empty() {
+        task_list.set_attributes(task_list.tasks.len() - 1, attributes).map_err(CommandError::TaskNotFound)?;
+    }
+    if let Some(context) = context_word(matches) {
+        task_list.set_task_context(task_list.tasks.len() - 1, context).map_err(CommandError::TaskNotFound)?;
+    }
+    if let Some(remind) = matches.duration("remind") {
+        task_list.set_task_reminder(task_list.tasks.len() - 1, Some(remind)).map_err(CommandError::TaskNotFound)?;
+    }
+
+    let index = task_list.tasks.len() - 1;
+    match hooks::on_add(&config, task_list.tasks[index].clone()) {
+        Ok((task, feedback)) => {
+            let message = format!("'{}' added.", task.title);
+            task_list.tasks[index] = task;
+            Ok(with_feedback(message, feedback))
+        }
+        Err(error) => {
+            task_list.tasks.pop();
+            Err(CommandError::HookError(error))
         }
     }
+}
 
-    if command.len() == 0 {
-        return Response::help("help");
+/// Appends what hook scripts printed to a command's message.
+fn with_feedback(message: String, feedback: Vec<String>) -> String {
+    match feedback.is_empty() {
+        true => message,
+        false => format!("{}\n{}", message, feedback.join("\n")),
     }
+}
 
-    let response = match command[0].as_str() {
-        "help" | "h" => {
-            match command.len() {
-                1 => Response::help("help"),
-                _ => Response::help(&command[1]),
-            }
+/// Runs the on-modify and on-complete hooks on every task that differs from `before`, the tasks as they
+/// were before the command. Changes a hook rejects are undone.
+pub(crate) fn run_modify_hooks(task_list: &mut TaskList, before: Vec<Task>, config: &Config) -> Result<Vec<String>, CommandError> {
+    let mut feedback = Vec::new();
+    let mut rejections = Vec::new();
+
+    for (index, original) in before.into_iter().enumerate() {
+        let changed = match task_list.tasks.get(index) {
+            Some(task) => serde_json::to_string(task).ok() != serde_json::to_string(&original).ok(),
+            None => false,
+        };
+        if !changed {
+            continue;
         }
-        "add" | "a" => {
-            if command.len() == 1 {
-                Err(CommandError::MissingRequiredArgument("Add".to_string(), "Title".to_string()))
-            } else {
-                let mut title: Option<String> = None;
-                let mut description: Option<String> = None;
-                let mut date: Option<Result<Date, DateTimeError>> = None;
-                let mut time: Option<Result<Time, DateTimeError>> = None;
-                let mut flag: bool = false;
-                let mut priority: Option<u8> = None;
-
-                let mut i = 1;
-                while i < command.len() {
-                    match command[i].as_str() {
-                        "help" | "-h" | "--help" => return Response::help("add"),
-                        "-t" | "--title" => {
-                            if i + 1 < command.len() {
-                                title = Some(command[i + 1].to_string());
-                                i += 1;
-                            } else {
-                                return Err(CommandError::MissingRequiredArgument("Add".to_string(), "Title".to_string()));
-                            }
-                        }
-                        "-d" | "--description" => {
-                            if i + 1 < command.len() {
-                                description = Some(command[i + 1].to_string());
-                                i += 1;
-                            } else {
-                                return Err(CommandError::InvalidArgument("Add".to_string(), "Description".to_string()));
-                            }
-                        }
-                        "due" | "-D" | "--date" => {
-                            if i + 1 < command.len() {
-                                date = Some(Date::parse(&command[i + 1]));
-                                i += 1;
-                            } else {
-                                return Err(CommandError::InvalidArgument("Add".to_string(), "Date".to_string()));
-                            }
-                        }
-                        "at" | "-T" | "--time" => {
-                            if i + 1 < command.len() {
-                                time = Some(Time::parse(&command[i + 1]));
-                                i += 1;
-                            } else {
-                                return Err(CommandError::InvalidArgument("Add".to_string(), "Time".to_string()));
-                            }
-                        }
-                        "flag" | "-f" | "--flag" => {
-                            flag = true;
-                        }
-                        "-p" | "--priority" => {
-                            if i + 1 < command.len() {
-                                match command[i + 1].parse::<u8>() {
-                                    Ok(p) => priority = Some(p),
-                                    Err(_) => return Err(CommandError::InvalidArgument("Add".to_string(), "Priority".to_string())),
-                                }
-                                i += 1;
-                            } else {
-                                return Err(CommandError::InvalidArgument("Add".to_string(), "Priority".to_string()));
-                            }
-                        }
-                        _ => {
-                            if title.is_none() && i == 1 {
-                                title = Some(command[i].to_string());
-                            } else {
-                                return Err(CommandError::InvalidArgument("Add".to_string(), command[i].to_string()));
-                            }
-                        }
-                    }
-                    i += 1;
-                }
 
-                if title.is_none() {
-                    return Err(CommandError::MissingRequiredArgument("Add".to_string(), "Title".to_string()));
-                } else {
-                    task_list.new_task(title.as_ref().unwrap().to_string(), description, date, time, priority, false, flag);
-                    let _ = save_tasks(task_list, config.clone());
-                    Ok(("'".to_owned() + &title.unwrap() + "' added.").to_string())
-                }
+        match hooks::on_modify(config, &original, task_list.tasks[index].clone()) {
+            Ok((task, more)) => {
+                task_list.tasks[index] = task;
+                feedback.extend(more);
             }
-        }
-        "del" | "delete" | "d" => {
-            if command.len() == 1 {
-                Err(CommandError::MissingRequiredArgument("Delete".to_string(), "ID".to_string()))
-            } else {
-                return if command[1] == "help" || command[1] == "-h" || command[1] == "--help" {
-                    Response::help("delete")
-                } else if command[1] == "all" || command[1] == "-a" || command[1] == "--all" {
-                    task_list.tasks = Vec::new();
-                    let _ = save_tasks(task_list, config.clone());
-                    Ok("All tasks deleted.".to_string())
-                } else {
-                    match command[1].parse::<usize>() {
-                        Ok(id) => {
-                            match task_list.remove_task(id - 1) {
-                                Ok(ok) => {
-                                    let _ = save_tasks(task_list, config.clone());
-                                    Ok(ok)
-                                }
-                                Err(_) => Err(CommandError::TaskNotFound(command[1].to_string())),
-                            }
-                        }
-                        Err(_) => Err(CommandError::InvalidArgument("Delete".to_string(), command[1].to_string())),
-                    }
-                };
+            Err(error) => {
+                task_list.tasks[index] = original;
+                rejections.push(error);
             }
         }
-        "complete" | "c" => {
-            if command.len() == 1 {
-                Err(CommandError::MissingRequiredArgument("Complete".to_string(), "ID".to_string()))
-            } else {
-                return if command[1] == "help" || command[1] == "-h" || command[1] == "--help" {
-                    Response::help("complete")
-                } else if command[1] == "all" || command[1] == "-a" || command[1] == "--all" {
-                    for task in &mut task_list.tasks {
-                        task.complete = true;
-                    }
-                    let _ = save_tasks(task_list, config.clone());
-                    Ok("All tasks completed.".to_string())
-                } else {
-                    match command[1].parse::<usize>() {
-                        Ok(id) => {
-                            match task_list.mark_task_complete(id - 1) {
-                                Ok(ok) => {
-                                    let _ = save_tasks(task_list, config.clone());
-                                    Ok(ok)
-                                }
-                                Err(_) => Err(CommandError::TaskNotFound(command[1].to_string())),
-                            }
-                        }
-                        Err(_) => Err(CommandError::InvalidArgument("Complete".to_string(), command[1].to_string())),
-                    }
-                };
+    }
+
+    match rejections.is_empty() {
+        true => Ok(feedback),
+        false => Err(CommandError::HookError(rejections.join("\n"))),
+    }
+}
+
+/// Reads the `name:value` attribute assignments given to 'add' or 'edit'.
+fn parse_attributes(matches: &Matches, config: &Config, operation: &str) -> Result<Vec<(String, Option<String>)>, CommandError> {
+    matches.texts("attribute").iter()
+        .filter(|word| !word.starts_with('@'))
+        .map(|assignment| attributes::parse_assignment(&config.attributes, assignment))
+        .collect::<Result<Vec<_>, String>>()
+        .map_err(|error| CommandError::InvalidValue(operation.to_string(), "Attribute".to_string(), error))
+}
+
+/// The `@context` among the words given to 'add' or 'edit'. `@none` removes the context.
+fn context_word(matches: &Matches) -> Option<Option<String>> {
+    matches.texts("attribute").iter().rev()
+        .find_map(|word| word.strip_prefix('@'))
+        .map(|context| Some(context.to_string()).filter(|context| !context.is_empty() && context != "none"))
+}
+
+fn delete(matches: &Matches, task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    let selected = select_tasks(matches, task_list, &config, "Delete")?;
+    if let [id] = selected[..] {
+        let feedback = hooks::on_delete(&config, &task_list.tasks[id]).map_err(CommandError::HookError)?;
+        return task_list.remove_task(id)
+            .map(|message| with_feedback(message, feedback))
+            .map_err(|_| CommandError::TaskNotFound((id + 1).to_string()));
+    }
+
+    let mut feedback = Vec::new();
+    let mut deleted = 0;
+    // From the back, so the remaining indexes stay valid.
+    for id in selected.into_iter().rev() {
+        match hooks::on_delete(&config, &task_list.tasks[id]) {
+            Ok(more) => {
+                feedback.extend(more);
+                let _ = task_list.remove_task(id);
+                deleted += 1;
             }
+            Err(error) => feedback.push(error),
         }
-        "incomplete" | "i" => {
-            if command.len() == 1 {
-                Err(CommandError::MissingRequiredArgument("Incomplete".to_string(), "ID".to_string()))
-            } else {
-                return if command[1] == "help" || command[1] == "-h" || command[1] == "--help" {
-                    Response::help("incomplete")
-                } else if command[1] == "all" || command[1] == "-a" || command[1] == "--all" {
-                    for task in &mut task_list.tasks {
-                        task.complete = false;
-                    }
-                    let _ = save_tasks(task_list, config.clone());
-                    Ok("All tasks marked incomplete.".to_string())
-                } else {
-                    match command[1].parse::<usize>() {
-                        Ok(id) => {
-                            match task_list.mark_task_incomplete(id - 1) {
-                                Ok(ok) => {
-                                    let _ = save_tasks(task_list, config.clone());
-                                    Ok(ok)
-                                }
-                                Err(_) => Err(CommandError::TaskNotFound(command[1].to_string())),
-                            }
-                        }
-                        Err(_) => Err(CommandError::InvalidArgument("Incomplete".to_string(), command[1].to_string())),
-                    }
-                };
+    }
+
+    Ok(with_feedback(format!("{} tasks moved to the trash.", deleted), feedback))
+}
+
+/// Sets the status of the tasks chosen by `select_tasks`.
+fn set_status(matches: &Matches, task_list: &mut TaskList, config: &Config, status: Status, operation: &str) -> Result<String, CommandError> {
+    let selected = select_tasks(matches, task_list, config, operation)?;
+    if let [id] = selected[..] {
+        return task_list.set_task_status(id, status).map_err(|_| CommandError::TaskNotFound((id + 1).to_string()));
+    }
+
+    for id in selected.iter() {
+        let _ = task_list.set_task_status(*id, status);
+    }
+    Ok(format!("{} tasks marked {}.", selected.len(), status.label().to_lowercase()))
+}
+
+fn complete(matches: &Matches, task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    set_status(matches, task_list, &config, Status::Done, "Complete")
+}
+
+fn incomplete(matches: &Matches, task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    set_status(matches, task_list, &config, Status::Todo, "Incomplete")
+}
+
+fn start(matches: &Matches, task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    set_status(matches, task_list, &config, Status::InProgress, "Start")
+}
+
+fn block(matches: &Matches, task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    set_status(matches, task_list, &config, Status::Blocked, "Block")
+}
+
+fn wait(matches: &Matches, task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    set_status(matches, task_list, &config, Status::Waiting, "Wait")
+}
+
+fn cancel(matches: &Matches, task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    set_status(matches, task_list, &config, Status::Cancelled, "Cancel")
+}
+
+fn list(matches: &Matches, task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    let sort = matches.text("sort").unwrap_or_else(|| "position".to_string());
+    if !SORT_KEYS.contains(&sort.as_str()) && !config.attributes.contains_key(&sort) {
+        let mut keys: Vec<&str> = SORT_KEYS.to_vec();
+        keys.extend(config.attributes.keys().map(|name| name.as_str()));
+        return Err(CommandError::InvalidValue("List".to_string(), "Sort".to_string(), format!("Expected one of: {}.", keys.join(", "))));
+    }
+
+    if matches.is_set("all_lists") {
+        return list_all_lists(matches, &sort, config);
+    }
+
+    if matches.is_set("all") {
+        return Ok(task_list.tasks_to_string(task_list.sort_tasks(task_list.tasks.clone(), &sort, &config), config));
+    }
+
+    let tasks = listed_tasks(matches, task_list, &config)?;
+    let tasks = task_list.sort_tasks(tasks, &sort, &config);
+    Ok(task_list.tasks_to_string(tasks, config))
+}
+
+/// Every list's tasks matching the options of 'list', under the name of their list.
+fn list_all_lists(matches: &Matches, sort: &str, config: Config) -> Result<String, CommandError> {
+    let mut response = String::new();
+    for name in config.list_names() {
+        let list_config = config.with_list(&name).map_err(CommandError::ConfigError)?;
+        let task_list = read_tasks(list_config.clone()).unwrap_or_else(|_| TaskList::new());
+        let tasks = match matches.is_set("all") {
+            true => task_list.tasks.clone(),
+            false => listed_tasks(matches, &task_list, &list_config)?,
+        };
+        if tasks.is_empty() {
+            continue;
+        }
+
+        let tasks = task_list.sort_tasks(tasks, sort, &list_config);
+        response.push_str(&format!("{}{}:\n", if name == config.current_list() { "* " } else { "" }, name));
+        response.push_str(&task_list.tasks_to_string(tasks, list_config));
+    }
+
+    match response.is_empty() {
+        true => Ok("No tasks in any list.".to_string()),
+        false => Ok(response),
+    }
+}
+
+/// Tasks matching the options of 'list' and the active context.
+fn listed_tasks(matches: &Matches, task_list: &TaskList, config: &Config) -> Result<Vec<Task>, CommandError> {
+    let context = config.context_filters().map_err(CommandError::ConfigError)?;
+    let mut filters: Vec<&str> = FILTER_NAMES
+        .into_iter()
+        .filter(|filter| matches.is_set(filter))
+        .collect();
+    let status = matches.text("status");
+    if let Some(status) = &status {
+        filters.push(status);
+    }
+    let terms = matches.texts("filter");
+    filters.extend(terms.iter().map(|term| term.as_str()));
+    if let Some(priority) = matches.priority("priority") {
+        filters.push(Priority::filter_name(priority));
+    }
+    filters.extend(context.iter().map(|filter| filter.as_str()));
+
+    if !filters.iter().any(|filter| *filter == "hidden" || filter.starts_with("wait")) {
+        filters.push("visible");
+    }
+
+    task_list.filter_tasks_with_attributes(filters, &config.attributes)
+        .map_err(|error| CommandError::InvalidValue("List".to_string(), "Filter".to_string(), error))
+}
+
+fn move_task(matches: &Matches, task_list: &mut TaskList, _config: Config) -> Result<String, CommandError> {
+    let id = required_id(matches, "Move")?;
+    let position = match matches.number("position") {
+        Some(position) if position > 0 => position as usize - 1,
+        _ => return Err(CommandError::InvalidValue("Move".to_string(), "Position".to_string(), "Positions start at 1.".to_string())),
+    };
+
+    task_list.move_task(id, position).map_err(|_| CommandError::TaskNotFound((id + 1).to_string()))
+}
+
+fn next(matches: &Matches, task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    let count = matches.number("count").unwrap_or(5) as usize;
+    let tasks = task_list.sort_tasks(task_list.filter_tasks(vec!["incomplete", "visible"]), "urgency", &config);
+
+    if tasks.is_empty() {
+        return Ok("Nothing to do.".to_string());
+    }
+
+    let theme = config.theme();
+    Ok(tasks.iter()
+        .take(count)
+        .map(|task| task.style(&theme).paint(&format!("{:>6.1}  {}: {}", task.urgency(&config.urgency), task.num, task.title)))
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+fn search(matches: &Matches, task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    let query = matches.text("query").ok_or_else(|| CommandError::MissingRequiredArgument("Search".to_string(), "Query".to_string()))?;
+    if matches.is_set("regex") && matches.is_set("fuzzy") {
+        return Err(CommandError::InvalidArgument("Search".to_string(), "--regex with --fuzzy".to_string()));
+    }
+    let matcher = Matcher::new(&query, matches.is_set("regex"), matches.is_set("fuzzy"), matches.is_set("exact"))
+        .map_err(|error| CommandError::InvalidValue("Search".to_string(), "Query".to_string(), error))?;
+    let chosen = matches.texts("in");
+    let fields: Vec<&'static str> = SEARCH_FIELDS.into_iter()
+        .filter(|field| chosen.is_empty() || chosen.iter().any(|chosen| chosen == field))
+        .collect();
+
+    let context = config.context_filters().map_err(CommandError::ConfigError)?;
+    let in_context: Vec<Task> = task_list.filter_indexes_with_attributes(context.iter().map(|filter| filter.as_str()).collect(), &config.attributes)
+        .map_err(CommandError::ConfigError)?
+        .into_iter()
+        .map(|index| task_list.tasks[index].clone())
+        .collect();
+    let hits = search::search(&in_context, &matcher, &fields);
+
+    let theme = config.theme();
+    let mut response = search::hits_to_string(&hits, &theme);
+    if matches.is_set("archive") {
+        let archive = read_archive(&config).map_err(|error| CommandError::ConfigError(error.to_string()))?;
+        let archived = search::search(&archive.tasks, &matcher, &fields);
+        if !archived.is_empty() {
+            if !response.is_empty() {
+                response.push('\n');
             }
+            response.push_str("Archived:\n");
+            response.push_str(&search::hits_to_string(&archived, &theme));
         }
-        "search" | "s" => {
-            if command.len() == 1 {
-                Err(CommandError::MissingRequiredArgument("Search".to_string(), "Query".to_string()))
-            } else {
-                return if command[1] == "help" || command[1] == "-h" || command[1] == "--help" {
-                    Response::help("search")
-                } else {
-                    let mut exact = false;
-                    let query: String;
-
-                    if command[1] == "-e" || command[1] == "--exact" {
-                        if command.len() == 2 {
-                            return Err(CommandError::MissingRequiredArgument("Search".to_string(), "Query".to_string()));
-                        } else {
-                            exact = true;
-                            query = command[2].to_string();
-                        }
-                    } else {
-                        if command.contains(&"-e".to_string()) || command.contains(&"--exact".to_string()) {
-                            exact = true;
-                        }
-
-                        query = command[1].to_string();
-                    }
-
-                    Ok(task_list.search_tasks_to_string(query, exact, config.clone()))
-                };
+    }
+
+    match response.is_empty() {
+        true => Ok(format!("No tasks match '{}'.", query)),
+        false => Ok(response),
+    }
+}
+
+fn edit(matches: &Matches, task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    let properties = ["title", "description", "date", "time", "scheduled", "wait", "until", "flag", "priority", "project", "estimate", "remind", "tag", "untag", "notes", "attribute"];
+    let attributes = parse_attributes(matches, &config, "Edit")?;
+    let selected = select_tasks(matches, task_list, &config, "Edit")?;
+
+    if !properties.iter().any(|property| matches.is_set(property)) {
+        return match selected[..] {
+            [id] => form::edit_in_editor(task_list, id, &config.attributes),
+            _ => Err(CommandError::InvalidValue("Edit".to_string(), "Tasks".to_string(), "Give the options to change when editing several tasks.".to_string())),
+        };
+    }
+    if matches.is_set("notes") && selected.len() > 1 {
+        return Err(CommandError::InvalidValue("Edit".to_string(), "Notes".to_string(), "Notes are edited one task at a time.".to_string()));
+    }
+
+    for id in selected.iter() {
+        edit_one(matches, task_list, *id, attributes.clone())?;
+    }
+
+    match selected.len() {
+        1 => Ok("Task successfully edited.".to_string()),
+        count => Ok(format!("{} tasks edited.", count)),
+    }
+}
+
+/// Applies the options given to 'edit' to the task at `id`.
+fn edit_one(matches: &Matches, task_list: &mut TaskList, id: usize, attributes: Vec<(String, Option<String>)>) -> Result<(), CommandError> {
+    let flag = if matches.is_set("flag") { Some(true) } else { None };
+    // An empty project removes the task from its project.
+    let project = matches.text("project").map(|project| Some(project).filter(|project| !project.is_empty()));
+
+    let edited = task_list.edit_task(id, matches.text("title"), matches.text("description"), matches.date("date").map(Ok), matches.time("time").map(Ok), flag, matches.priority("priority"), project, matches.duration("estimate").map(Some))
+        .and_then(|_| match ["scheduled", "wait", "until"].iter().any(|date| matches.is_set(date)) {
+            true => task_list.schedule_task(id, matches.date("scheduled").map(Some), matches.date("wait").map(Some), matches.date("until").map(Some)),
+            false => Ok(String::new()),
+        })
+        .and_then(|_| match matches.is_set("tag") || matches.is_set("untag") {
+            true => task_list.retag_task(id, matches.texts("tag"), matches.texts("untag")),
+            false => Ok(String::new()),
+        })
+        .and_then(|_| match attributes.is_empty() {
+            true => Ok(String::new()),
+            false => task_list.set_attributes(id, attributes),
+        })
+        .and_then(|_| match context_word(matches) {
+            Some(context) => task_list.set_task_context(id, context),
+            None => Ok(String::new()),
+        })
+        .and_then(|_| match matches.duration("remind") {
+            Some(remind) => task_list.set_task_reminder(id, Some(remind)),
+            None => Ok(String::new()),
+        });
+
+    if edited.is_err() {
+        return Err(CommandError::TaskNotFound((id + 1).to_string()));
+    }
+
+    if matches.is_set("notes") {
+        let notes = task_list.tasks[id].notes.clone().unwrap_or_default();
+        let notes = editor::edit_text(&notes, "md").map_err(CommandError::EditorError)?;
+        let _ = task_list.set_task_notes(id, notes);
+    }
+
+    Ok(())
+}
+
+fn annotate(matches: &Matches, task_list: &mut TaskList, _config: Config) -> Result<String, CommandError> {
+    let id = required_id(matches, "Annotate")?;
+    if id >= task_list.tasks.len() {
+        return Err(CommandError::TaskNotFound((id + 1).to_string()));
+    }
+
+    match (matches.number("remove"), matches.text("text")) {
+        (Some(number), _) => task_list.remove_annotation(id, number as usize).map_err(|error| CommandError::InvalidValue("Annotate".to_string(), "Remove".to_string(), error)),
+        (None, Some(text)) => task_list.annotate_task(id, text).map_err(|_| CommandError::TaskNotFound((id + 1).to_string())),
+        (None, None) => Err(CommandError::MissingRequiredArgument("Annotate".to_string(), "Text".to_string())),
+    }
+}
+
+fn track(matches: &Matches, task_list: &mut TaskList, _config: Config) -> Result<String, CommandError> {
+    let not_found = |id: usize| CommandError::TaskNotFound((id + 1).to_string());
+
+    match matches.text("action").as_deref() {
+        Some("start") => {
+            let id = required_id(matches, "Track")?;
+            task_list.start_timer(id).map_err(|_| not_found(id))
+        }
+        Some("stop") => Ok(task_list.stop_timer().unwrap_or_else(|message| message)),
+        Some("add") => {
+            let id = required_id(matches, "Track")?;
+            let duration = matches.duration("duration").ok_or_else(|| CommandError::MissingRequiredArgument("Track".to_string(), "Duration".to_string()))?;
+            task_list.add_time(id, duration).map_err(|_| not_found(id))
+        }
+        _ => match task_list.active_timer() {
+            Some(index) => {
+                let task = &task_list.tasks[index];
+                let running = task.time_entries.last().map(|entry| entry.duration()).unwrap_or_else(chrono::Duration::zero);
+                Ok(format!("Tracking '{}' for {}. Total: {}.", task.title, format_duration(running), format_duration(task.tracked())))
             }
+            None => Ok("No timer is running.".to_string()),
         },
-        "edit" | "e" => {
-            if command.len() == 1 {
-                Err(CommandError::MissingRequiredArgument("Edit".to_string(), "ID".to_string()))
-            } else if command.len() == 2 {
-                return if command[1] == "help" || command[1] == "-h" || command[1] == "--help" {
-                    Response::help("edit")
-                } else {
-                    Err(CommandError::MissingRequiredArgument("Edit".to_string(), "Property".to_string()))
+    }
+}
+
+fn report(matches: &Matches, task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    let name = matches.text("report").ok_or_else(|| CommandError::MissingRequiredArgument("Report".to_string(), "Report".to_string()))?;
+    match name.as_str() {
+        "effort" => Ok(report::effort(task_list)),
+        "waiting" => Ok(report::waiting(task_list, config)),
+        name => {
+            let saved = config.reports.get(name).cloned().ok_or_else(|| {
+                let mut names: Vec<&str> = report::REPORT_NAMES.to_vec();
+                names.extend(config.reports.keys().map(|name| name.as_str()));
+                CommandError::InvalidValue("Report".to_string(), "Report".to_string(), format!("'{}' is not a report. Expected one of: {}.", name, names.join(", ")))
+            })?;
+
+            let mut filters: Vec<&str> = saved.filter.split_whitespace().collect();
+            if !filters.iter().any(|filter| *filter == "hidden" || filter.starts_with("wait")) {
+                filters.push("visible");
+            }
+            let tasks = task_list.filter_tasks_with_attributes(filters, &config.attributes)
+                .map_err(|error| CommandError::ConfigError(format!("Report '{}': {}", name, error)))?;
+            let tasks = task_list.sort_tasks(tasks, saved.sort.as_deref().unwrap_or("position"), &config);
+            Ok(report::saved(task_list, tasks, &saved, config))
+        }
+    }
+}
+
+fn context(matches: &Matches, task_list: &mut TaskList, mut config: Config) -> Result<String, CommandError> {
+    let name = || matches.text("name").ok_or_else(|| CommandError::MissingRequiredArgument("Context".to_string(), "Name".to_string()));
+    let save = |config: &Config, message: String| config.save_to_file().map(|_| message).map_err(|error| CommandError::ConfigError(error.to_string()));
+
+    match matches.text("action").as_deref() {
+        Some("set") => {
+            let name = name()?;
+            let expression = config.contexts.get(&name).cloned().ok_or_else(|| {
+                let names: Vec<&str> = config.contexts.keys().map(|name| name.as_str()).collect();
+                CommandError::InvalidValue("Context".to_string(), "Name".to_string(), format!("'{}' is not defined. Defined contexts: {}.", name, if names.is_empty() { "none".to_string() } else { names.join(", ") }))
+            })?;
+            config.context = Some(name.clone());
+            save(&config, format!("Context '{}' set. 'list' and 'search' now only show tasks matching '{}'.", name, expression))
+        }
+        Some("clear") => {
+            config.context = None;
+            save(&config, "Context cleared.".to_string())
+        }
+        Some("define") => {
+            let name = name()?;
+            let filters = matches.texts("filter");
+            if filters.is_empty() {
+                if config.contexts.remove(&name).is_none() {
+                    return Err(CommandError::MissingRequiredArgument("Context".to_string(), "Filter".to_string()));
                 }
-            } else if command[1] == "help" || command[1] == "-h" || command[1] == "--help" {
-                Response::help("edit")
-            } else {
-                let id = match command[1].parse::<usize>() {
-                    Ok(id) => id - 1,
-                    Err(_) => return Err(CommandError::InvalidArgument("Edit".to_string(), command[1].to_string())),
-                };
-
-                let mut title: Option<String> = None;
-                let mut description: Option<String> = None;
-                let mut date: Option<Result<Date, DateTimeError>> = None;
-                let mut time: Option<Result<Time, DateTimeError>> = None;
-                let mut flag: Option<bool> = None;
-                let mut priority: Option<u8> = None;
-
-                let mut i = 2;
-                while i < command.len() {
-                    match command[i].as_str() {
-                        "help" | "-h" | "--help" => return Response::help("Edit"),
-                        "-t" | "--title" => {
-                            if i + 1 < command.len() {
-                                title = Some(command[i + 1].to_string());
-                                i += 1;
-                            } else {
-                                return Err(CommandError::InvalidArgument("Edit".to_string(), "Title".to_string()));
-                            }
-                        }
-                        "-d" | "--description" => {
-                            if i + 1 < command.len() {
-                                description = Some(command[i + 1].to_string());
-                                i += 1;
-                            } else {
-                                return Err(CommandError::InvalidArgument("Edit".to_string(), "Description".to_string()));
-                            }
-                        }
-                        "due" | "-D" | "--date" => {
-                            if i + 1 < command.len() {
-                                date = Some(Date::parse(&command[i + 1]));
-                                i += 1;
-                            } else {
-                                return Err(CommandError::InvalidArgument("Edit".to_string(), "Date".to_string()));
-                            }
-                        }
-                        "at" | "-T" | "--time" => {
-                            if i + 1 < command.len() {
-                                time = Some(Time::parse(&command[i + 1]));
-                                i += 1;
-                            } else {
-                                return Err(CommandError::InvalidArgument("Edit".to_string(), "Time".to_string()));
-                            }
-                        }
-                        "flag" | "-f" | "--flag" => {
-                            flag = Some(true);
-                        }
-                        "-p" | "--priority" => {
-                            if i + 1 < command.len() {
-                                match command[i + 1].parse::<u8>() {
-                                    Ok(p) => priority = Some(p),
-                                    Err(_) => return Err(CommandError::InvalidArgument("Edit".to_string(), "Priority".to_string())),
-                                }
-                                i += 1;
-                            } else {
-                                return Err(CommandError::InvalidArgument("Edit".to_string(), "Priority".to_string()));
-                            }
-                        }
-                        _ => {
-                            return Err(CommandError::InvalidArgument("Edit".to_string(), command[i].to_string()));
-                        }
-                    }
-                    i += 1;
+                if config.context.as_ref() == Some(&name) {
+                    config.context = None;
                 }
+                return save(&config, format!("Context '{}' removed.", name));
+            }
 
-                let task_response = task_list.edit_task(id, title, description, date, time, flag, priority);
-                let _ = save_tasks(task_list, config.clone());
+            task_list.filter_tasks_with_attributes(filters.iter().map(|filter| filter.as_str()).collect(), &config.attributes)
+                .map_err(|error| CommandError::InvalidValue("Context".to_string(), "Filter".to_string(), error))?;
+            config.contexts.insert(name.clone(), filters.join(" "));
+            save(&config, format!("Context '{}' defined.", name))
+        }
+        Some("list") => match config.contexts.is_empty() {
+            true => Ok("No contexts defined. Use 'taskninja context define NAME FILTER...' to add one.".to_string()),
+            false => Ok(config.contexts.iter()
+                .map(|(name, expression)| format!("{} {:<16}{}", if config.context.as_ref() == Some(name) { "*" } else { " " }, name, expression))
+                .collect::<Vec<String>>()
+                .join("\n")),
+        },
+        _ => match &config.context {
+            Some(name) => Ok(format!("Context '{}': {}", name, config.contexts.get(name).cloned().unwrap_or_else(|| "(not defined)".to_string()))),
+            None => Ok("No context set.".to_string()),
+        },
+    }
+}
 
-                match task_response {
-                    Ok(_) => {
-                        Ok("Task successfully edited.".to_string())
-                    }
-                    Err(_) => Err(CommandError::TaskNotFound(command[1].to_string())),
-                }
+fn use_list(matches: &Matches, _task_list: &mut TaskList, mut config: Config) -> Result<String, CommandError> {
+    let name = match matches.text("name") {
+        Some(name) => name,
+        None => {
+            let current = config.current_list();
+            return Ok(config.list_names().iter()
+                .map(|name| format!("{} {:<16}{}", if *name == current { "*" } else { " " }, name, config.list_file(name).unwrap_or_default()))
+                .collect::<Vec<String>>()
+                .join("\n"));
+        }
+    };
 
-            }
+    let mut message = format!("Now using list '{}'.", name);
+    if config.list_file(&name).is_none() {
+        let file = config.add_list(&name).map_err(|error| CommandError::InvalidValue("Use".to_string(), "Name".to_string(), error))?;
+        message = format!("Created list '{}' in {}. {}", name, file, message);
+    }
+    config.list = (name != DEFAULT_LIST).then_some(name);
+    config.save_to_file().map(|_| message).map_err(|error| CommandError::ConfigError(error.to_string()))
+}
+
+fn transfer(matches: &Matches, task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    let name = matches.text("to").ok_or_else(|| CommandError::MissingRequiredArgument("Transfer".to_string(), "To".to_string()))?;
+    if name == config.current_list() {
+        return Err(CommandError::InvalidValue("Transfer".to_string(), "To".to_string(), format!("The tasks are already in list '{}'.", name)));
+    }
+    let target_config = config.with_list(&name).map_err(|error| CommandError::InvalidValue("Transfer".to_string(), "To".to_string(), error))?;
+    if target_config.tasks_file() == config.tasks_file() {
+        return Err(CommandError::InvalidValue("Transfer".to_string(), "To".to_string(), format!("List '{}' is kept in the same file, {}.", name, config.tasks_file())));
+    }
+
+    let indexes = select_tasks(matches, task_list, &config, "Transfer")?;
+
+    // A target that exists but can't be read must not be overwritten with an empty list.
+    let mut target = match Path::new(&target_config.tasks_file()).exists() {
+        true => read_tasks(target_config.clone()).map_err(|error| CommandError::ConfigError(error.to_string()))?,
+        false => TaskList::new(),
+    };
+    for index in &indexes {
+        target.add_task(task_list.tasks[*index].clone());
+    }
+    target.sort_and_renew();
+    // Tasks only leave this list once they're safely in the other one.
+    save_tasks(&target, target_config).map_err(|error| CommandError::ConfigError(error.to_string()))?;
+
+    let count = indexes.len();
+    if !matches.is_set("copy") {
+        task_list.take_tasks(&indexes);
+    }
+
+    let verb = if matches.is_set("copy") { "copied" } else { "moved" };
+    Ok(format!("{} task{} {} to list '{}'.", count, if count == 1 { "" } else { "s" }, verb, name))
+}
+
+fn trash(matches: &Matches, task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    match matches.text("action").as_deref() {
+        Some("restore") => {
+            let number = matches.number("number").ok_or_else(|| CommandError::MissingRequiredArgument("Trash".to_string(), "Number".to_string()))?;
+            task_list.restore_task(number as usize).map_err(|_| CommandError::TaskNotFound(number.to_string()))
+        }
+        Some("empty") => Ok(task_list.empty_trash()),
+        _ => match task_list.trash.is_empty() {
+            true => Ok("The trash is empty.".to_string()),
+            false => Ok(task_list.trash.iter().enumerate()
+                .map(|(i, trashed)| format!("{:>4}: {}  (deleted {})", i + 1, trashed.task.title, format_timestamp(&trashed.deleted_at, &config)))
+                .collect::<Vec<String>>()
+                .join("\n")),
         },
-        "list" | "l" => {
-            if command.len() == 1 {
-                Ok(task_list.to_string(config.clone()))
-            } else {
-                let mut filters: Vec<&str> = Vec::new();
-                for i in command[1..].iter() {
-                    match i.as_str() {
-                        "h" | "help" | "-h" | "--help" => { return Response::help("list"); }
-                        "a" | "all" | "-a" | "--all" => { return Ok(task_list.to_string(config.clone())); }
-                        "-c" | "--complete" => { filters.push("complete"); }
-                        "-i" | "--incomplete" => { filters.push("incomplete"); }
-                        "-f" | "--flagged" => { filters.push("flagged"); }
-                        "-u" | "--unflagged" => { filters.push("unflagged"); }
-                        "-t" | "--today" => { filters.push("today"); }
-                        _ => { return Err(CommandError::InvalidArgument("List".to_string(), i.to_string())); }
-                    }
-                }
+    }
+}
 
-                Ok(task_list.filter_tasks_to_string(filters, config.clone()))
-            }
+fn archive(matches: &Matches, task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    let archive_error = |error: SaveError| CommandError::ArchiveError(match error {
+        SaveError::FailedRead(error) => format!("Could not read {}.", error),
+        SaveError::FailedSave(error) => format!("Could not write {}.", error),
+    });
+    let days = matches.number("days").unwrap_or(30);
+
+    let closed = task_list.closed_before(days as i64);
+    if closed.is_empty() {
+        return Ok(format!("No tasks closed more than {} days ago.", days));
+    }
+
+    let mut archive = read_archive(&config).map_err(archive_error)?;
+    for index in &closed {
+        archive.add_task(task_list.tasks[*index].clone());
+    }
+    save_archive(&archive, &config).map_err(archive_error)?;
+    // Tasks only leave the list once the archive holding them is written.
+    let count = task_list.take_tasks(&closed).len();
+
+    Ok(format!("{} task{} archived to {}.", count, if count == 1 { "" } else { "s" }, config.archive_file))
+}
+
+fn daemon(_matches: &Matches, _task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    daemon::start(&config).map_err(CommandError::ConfigError)
+}
+
+fn configure(matches: &Matches, _task_list: &mut TaskList, mut config: Config) -> Result<String, CommandError> {
+    let key = || matches.text("key").ok_or_else(|| CommandError::MissingRequiredArgument("Config".to_string(), "Key".to_string()));
+    let invalid = |error: String| CommandError::InvalidValue("Config".to_string(), "Setting".to_string(), error);
+    let show = |settings: Vec<(String, serde_json::Value)>| settings.into_iter()
+        .map(|(key, value)| format!("{} = {}", key, match value {
+            serde_json::Value::String(text) => text,
+            serde_json::Value::Null => "(not set)".to_string(),
+            value => value.to_string(),
+        }))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let message = match matches.text("action").as_deref() {
+        Some("get") => return config.get(&key()?).map(show).map_err(invalid),
+        Some("set") => {
+            let key = key()?;
+            let value = matches.texts("value").join(" ");
+            config.set(&key, &value).map_err(invalid)?;
+            show(config.get(&key).map_err(invalid)?)
         }
-        _ => Err(CommandError::InvalidMainOperation(command[0].to_string()))
+        Some("reset") => match matches.text("key") {
+            Some(key) => {
+                config.reset(Some(&key)).map_err(invalid)?;
+                format!("'{}' reset.", key)
+            }
+            None => {
+                config.reset(None).map_err(CommandError::ConfigError)?;
+                "Settings reset to their defaults.".to_string()
+            }
+        },
+        _ => return Ok(show(config.settings())),
     };
 
+    config.save_to_file().map(|_| message).map_err(|error| CommandError::ConfigError(error.to_string()))
+}
+
+fn doctor(_matches: &Matches, task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    let status = |path: &str| if Path::new(path).exists() { "" } else { ", not found" };
+    let data_source = match &config.paths.data {
+        Some(data) => data.source.clone(),
+        None => format!("list '{}'", config.current_list()),
+    };
+    let data_file = config.tasks_file();
+    let history = history_path(&config).to_string_lossy().to_string();
+
+    let mut lines = vec![
+        format!("{:<16}{}  ({}{})", "Config file:", config.paths.config.path, config.paths.config.source, status(&config.paths.config.path)),
+        format!("{:<16}{}  ({}{})", "Data file:", data_file, data_source, status(&data_file)),
+        format!("{:<16}{}  (archive_file{})", "Archive file:", config.archive_file, status(&config.archive_file)),
+        format!("{:<16}{}  (hooks_dir{})", "Hooks:", config.hooks_dir, status(&config.hooks_dir)),
+        format!("{:<16}{}  ({}{})", "Shell history:", history, "next to data_file", status(&history)),
+        String::new(),
+    ];
+
+    let checks = [
+        ("Theme", config.check_theme().map(|_| ()).map_err(|error| error.to_string())),
+        ("Attributes", check_attributes(&config.attributes)),
+        ("Context", config.context_filters().map(|_| ())),
+        ("Data file", match Path::new(&data_file).exists() {
+            true => read_tasks(config.clone()).map(|_| ()).map_err(|error| error.to_string()),
+            false => Ok(()),
+        }),
+    ];
+    for (name, result) in checks {
+        lines.push(format!("{:<16}{}", format!("{}:", name), result.err().unwrap_or_else(|| "OK".to_string())));
+    }
+    lines.push(format!("{:<16}{}", "Tasks:", task_list.tasks.len()));
+
+    Ok(lines.join("\n"))
+}
+
+fn tui(_matches: &Matches, task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    tui::run(task_list, config)
+}
+
+fn shell(matches: &Matches, task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    shell::run(task_list, config, !matches.is_set("no_autosave"))
+}
+
+fn completions(matches: &Matches, task_list: &mut TaskList, _config: Config) -> Result<String, CommandError> {
+    if matches.is_set("tasks") {
+        return Ok(completions::task_candidates(task_list));
+    }
+
+    match matches.text("shell") {
+        Some(shell) => completions::script(&shell).ok_or(CommandError::InvalidArgument("Completions".to_string(), shell)),
+        None => Err(CommandError::MissingRequiredArgument("Completions".to_string(), "Shell".to_string())),
+    }
+}
+
+pub(crate) fn command_handler(command: Vec<String>, config: Config) -> Result<String, CommandError> {
+    let mut task_list: TaskList;
+
+    match read_tasks(config.clone()) {
+        Ok(tasks) => {
+            task_list = tasks;
+        }
+        Err(_) => {
+            task_list = TaskList::new();
+        }
+    }
+
+    let launch = hooks::on_launch(&config).map_err(CommandError::HookError)?;
+    let response = run_command(command, &mut task_list, config.clone()).map(|message| match launch.is_empty() {
+        true => message,
+        false => format!("{}\n{}", launch.join("\n"), message),
+    });
+
+    if task_list.modified {
+        save_tasks(&task_list, config).map_err(|error| CommandError::ConfigError(format!("{}. The changes were not saved.", error)))?;
+    }
+
     response
-}
\ No newline at end of file
+}
+
+/// Reports the first alias that a built-in command would hide.
+pub(crate) fn check_aliases(aliases: &BTreeMap<String, String>) -> Result<(), String> {
+    match aliases.keys().find(|name| find_command(name).is_some()) {
+        Some(name) => Err(format!("Alias '{}': the name is already used by a command.", name)),
+        None => Ok(()),
+    }
+}
+
+/// Replaces an alias at the front of `command` with the command line it stands for, keeping the
+/// arguments after it. Aliases may start with other aliases.
+fn expand_alias(mut command: Vec<String>, config: &Config) -> Result<Vec<String>, CommandError> {
+    let mut expanded: Vec<String> = Vec::new();
+
+    while let Some(expansion) = command.first().filter(|name| find_command(name).is_none()).and_then(|name| config.aliases.get(name)) {
+        let name = command.remove(0);
+        if expanded.contains(&name) {
+            return Err(CommandError::ConfigError(format!("Alias '{}' expands to itself.", name)));
+        }
+
+        let mut words = split_line(expansion).map_err(|error| CommandError::ConfigError(format!("Alias '{}': {}", name, error)))?;
+        if words.is_empty() {
+            return Err(CommandError::ConfigError(format!("Alias '{}' is empty.", name)));
+        }
+        words.append(&mut command);
+        command = words;
+        expanded.push(name);
+    }
+
+    Ok(command)
+}
+
+/// Runs a single command against an already loaded task list. Changes are not saved,
+/// `TaskList::modified` tells the caller whether the list needs to be written back.
+pub(crate) fn run_command(command: Vec<String>, task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    if command.is_empty() {
+        return Response::help("help");
+    }
+
+    let command = expand_alias(command, &config)?;
+    let spec = find_command(&command[0]).ok_or_else(|| CommandError::InvalidMainOperation(command[0].to_string()))?;
+    let matches = spec.parse(&command[1..])?;
+
+    if matches.help {
+        return Ok(spec.help());
+    }
+
+    if !MODIFYING.contains(&spec.name) {
+        return (spec.handler)(&matches, task_list, config);
+    }
+
+    let before = task_list.tasks.clone();
+    let message = (spec.handler)(&matches, task_list, config.clone())?;
+    let feedback = run_modify_hooks(task_list, before, &config)?;
+    Ok(with_feedback(message, feedback))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task_list(count: usize) -> TaskList {
+        let mut task_list = TaskList::new();
+        task_list.tasks = (1..=count).map(|n| Task::from(format!("Task {}", n), None, None, None, None, None, None, Status::Todo, false)).collect();
+        task_list.renew();
+        task_list
+    }
+
+    fn config() -> Config {
+        Config { hooks_dir: "/nonexistent/taskninja-hooks".to_string(), ..Config::default() }
+    }
+
+    fn select(arguments: &[&str], task_list: &TaskList) -> Result<Vec<usize>, CommandError> {
+        let arguments: Vec<String> = arguments.iter().map(|argument| argument.to_string()).collect();
+        let matches = find_command("complete").unwrap().parse(&arguments)?;
+        select_tasks(&matches, task_list, &config(), "Complete")
+    }
+
+    #[test]
+    fn ids_and_ranges_select_by_position() {
+        let task_list = task_list(10);
+        assert_eq!(select(&["2"], &task_list), Ok(vec![1]));
+        assert_eq!(select(&["1,3", "5-6", "-y"], &task_list), Ok(vec![0, 2, 4, 5]));
+        assert_eq!(select(&["3,1-2,2"], &task_list), Ok(vec![0, 1, 2]));
+        assert_eq!(select(&["all", "-y"], &task_list), Ok((0..10).collect()));
+    }
+
+    #[test]
+    fn filters_narrow_down_ids() {
+        let mut task_list = task_list(6);
+        for index in [1, 3, 5] {
+            task_list.tasks[index].tags = vec!["even".to_string()];
+        }
+        assert_eq!(select(&["tag:even"], &task_list), Ok(vec![1, 3, 5]));
+        assert_eq!(select(&["1-4", "tag:even"], &task_list), Ok(vec![1, 3]));
+    }
+
+    #[test]
+    fn bad_selections_are_errors() {
+        let task_list = task_list(3);
+        assert_eq!(select(&[], &task_list), Err(CommandError::MissingRequiredArgument("Complete".to_string(), "Tasks".to_string())));
+        assert_eq!(select(&["2-5"], &task_list), Err(CommandError::TaskNotFound("4".to_string())));
+        assert!(matches!(select(&["tag:none"], &task_list), Err(CommandError::InvalidValue(..))));
+        assert!(matches!(select(&["bogus"], &task_list), Err(CommandError::InvalidValue(..))));
+    }
+
+    #[test]
+    fn filtered_commands_change_only_matching_tasks_past_255() {
+        let mut task_list = task_list(300);
+        task_list.tasks[0].tags = vec!["only".to_string()];
+
+        run_command(vec!["complete".to_string(), "tag:only".to_string()], &mut task_list, config()).unwrap();
+
+        let done: Vec<usize> = (0..300).filter(|index| task_list.tasks[*index].status == Status::Done).collect();
+        assert_eq!(done, vec![0]);
+    }
+}
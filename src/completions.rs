@@ -0,0 +1,160 @@
+use crate::TaskList;
+use crate::cli::{Command, ValueType};
+use crate::command_handler::COMMANDS;
+
+const PROGRAM: &str = "taskninja";
+
+/// Prints one `ID<TAB>title` line per task. The completion scripts call
+/// `taskninja completions --tasks` to offer task IDs.
+pub(crate) fn task_candidates(task_list: &TaskList) -> String {
+    task_list.tasks.iter()
+        .map(|task| format!("{}\t{}", task.num, task.title.split_whitespace().collect::<Vec<&str>>().join(" ")))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// The completion script for `shell`, or `None` if the shell isn't supported.
+pub(crate) fn script(shell: &str) -> Option<String> {
+    match shell {
+        "bash" => Some(bash()),
+        "zsh" => Some(zsh()),
+        "fish" => Some(fish()),
+        _ => None,
+    }
+}
+
+fn names(command: &Command) -> Vec<&'static str> {
+    std::iter::once(command.name).chain(command.aliases.iter().copied()).collect()
+}
+
+fn all_names() -> Vec<&'static str> {
+    COMMANDS.iter().flat_map(names).collect()
+}
+
+fn takes_id(command: &Command) -> bool {
+    matches!(command.positional_type(), Some(ValueType::Id | ValueType::Tasks))
+}
+
+/// Words offered after `command`, apart from task IDs.
+fn first_arguments(command: &Command) -> Vec<&'static str> {
+    let mut words = command.flags();
+    match command.positional_type() {
+        Some(ValueType::Choice(choices)) => words.extend(choices),
+        Some(ValueType::Command) => words.extend(COMMANDS.iter().map(|command| command.name)),
+        _ => {}
+    }
+    words
+}
+
+fn bash() -> String {
+    let mut cases = String::new();
+    for command in COMMANDS.iter() {
+        cases.push_str(&format!(
+            "        {})\n            arguments=\"{}\"\n            takes_id={}\n            ;;\n",
+            names(command).join("|"),
+            first_arguments(command).join(" "),
+            if takes_id(command) { 1 } else { 0 },
+        ));
+    }
+
+    format!(r#"# bash completion for {program}
+_{program}() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}"
+    local arguments="" takes_id=0
+
+    if [ "$COMP_CWORD" -eq 1 ]; then
+        COMPREPLY=($(compgen -W "{operations}" -- "$cur"))
+        return
+    fi
+
+    case "${{COMP_WORDS[1]}}" in
+{cases}    esac
+
+    if [ "$COMP_CWORD" -eq 2 ] && [ "$takes_id" -eq 1 ]; then
+        arguments="$arguments $({program} completions --tasks 2>/dev/null | cut -f1)"
+    fi
+
+    COMPREPLY=($(compgen -W "$arguments" -- "$cur"))
+}}
+
+complete -F _{program} {program}
+"#, program = PROGRAM, operations = all_names().join(" "), cases = cases)
+}
+
+fn zsh() -> String {
+    let mut operations = String::new();
+    for command in COMMANDS.iter() {
+        for name in names(command) {
+            operations.push_str(&format!("        '{}:{}'\n", name, command.summary.replace('\'', "'\\''")));
+        }
+    }
+
+    let mut cases = String::new();
+    for command in COMMANDS.iter() {
+        cases.push_str(&format!(
+            "        {})\n            arguments=({})\n            takes_id={}\n            ;;\n",
+            names(command).join("|"),
+            first_arguments(command).iter().map(|word| format!("'{}'", word)).collect::<Vec<String>>().join(" "),
+            if takes_id(command) { 1 } else { 0 },
+        ));
+    }
+
+    format!(r#"#compdef {program}
+
+_{program}() {{
+    local -a operations arguments tasks
+    local takes_id=0
+
+    operations=(
+{operations}    )
+
+    if (( CURRENT == 2 )); then
+        _describe 'operation' operations
+        return
+    fi
+
+    case $words[2] in
+{cases}    esac
+
+    if (( CURRENT == 3 && takes_id )); then
+        tasks=(${{(f)"$({program} completions --tasks 2>/dev/null | sed 's/:/\\:/g; s/\t/:/')"}})
+        _describe 'task' tasks
+    fi
+
+    compadd -a arguments
+}}
+
+compdef _{program} {program}
+"#, program = PROGRAM, operations = operations, cases = cases)
+}
+
+fn fish() -> String {
+    let mut script = format!("# fish completion for {program}\ncomplete -c {program} -f\n\n", program = PROGRAM);
+
+    for command in COMMANDS.iter() {
+        for name in names(command) {
+            script.push_str(&format!(
+                "complete -c {} -n '__fish_use_subcommand' -a '{}' -d '{}'\n",
+                PROGRAM, name, command.summary.replace('\'', "\\'"),
+            ));
+        }
+    }
+    script.push('\n');
+
+    for command in COMMANDS.iter() {
+        let condition = format!("__fish_seen_subcommand_from {}", names(command).join(" "));
+        let words = first_arguments(command);
+
+        if !words.is_empty() {
+            script.push_str(&format!("complete -c {} -n '{}' -a '{}'\n", PROGRAM, condition, words.join(" ")));
+        }
+        if takes_id(command) {
+            script.push_str(&format!(
+                "complete -c {program} -n '{condition}; and test (count (commandline -opc)) -eq 2' -a '({program} completions --tasks 2>/dev/null)'\n",
+                program = PROGRAM, condition = condition,
+            ));
+        }
+    }
+
+    script
+}
@@ -1,7 +1,123 @@
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
+use std::path::Path;
 use serde::{Serialize, Deserialize};
-use serde_json::to_writer_pretty;
+use serde_json::{to_writer_pretty, Value};
 use crate::SaveError;
+use crate::attributes::{check_attributes, Attributes};
+use crate::command_handler::check_aliases;
+use crate::paths::{self, Paths};
+use crate::report::{check_reports, SavedReport};
+use crate::theme::{colors_enabled, Style, Theme, ThemeError};
+
+fn default_theme() -> String {
+    String::from("dark")
+}
+
+/// Weights of the parts of a task's urgency score, see `Task::urgency`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Urgency {
+    /// Scaled from 0.2 for tasks due in two weeks or more to 1.0 for tasks due now.
+    pub due: f64,
+    /// Added on top of `due` once a task is past its due date.
+    pub overdue: f64,
+    /// Added once a task's scheduled date has arrived.
+    pub scheduled: f64,
+    pub flagged: f64,
+    pub priority_high: f64,
+    pub priority_medium: f64,
+    pub priority_low: f64,
+    /// Scaled by age, reaching the full value after a year.
+    pub age: f64,
+    /// Added when a task has any tags.
+    pub tags: f64,
+    /// Added for each of these tags a task has.
+    pub tag: HashMap<String, f64>,
+    pub in_progress: f64,
+    pub blocked: f64,
+    pub waiting: f64,
+}
+
+impl Default for Urgency {
+    fn default() -> Urgency {
+        Urgency {
+            due: 12.0,
+            overdue: 3.0,
+            scheduled: 5.0,
+            flagged: 4.0,
+            priority_high: 6.0,
+            priority_medium: 3.9,
+            priority_low: 1.8,
+            age: 2.0,
+            tags: 1.0,
+            tag: HashMap::new(),
+            in_progress: 4.0,
+            blocked: -5.0,
+            waiting: -3.0,
+        }
+    }
+}
+
+fn default_data_file() -> String {
+    paths::data_dir().join("tasks.json").to_string_lossy().to_string()
+}
+
+fn default_archive_file() -> String {
+    paths::data_dir().join("archive.json").to_string_lossy().to_string()
+}
+
+fn default_confirm_threshold() -> usize {
+    3
+}
+
+fn default_hooks_dir() -> String {
+    paths::config_dir().join("hooks").to_string_lossy().to_string()
+}
+
+fn default_all_day_time() -> String {
+    String::from("09:00")
+}
+
+fn default_interval() -> u64 {
+    30
+}
+
+/// How 'taskninja daemon' sends reminders. Without a command, log or socket they go to stdout.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Reminders {
+    /// Lead time of tasks without their own '--remind', e.g. "15m". Reminders are sent when tasks are due otherwise.
+    #[serde(default)]
+    pub lead: Option<String>,
+    /// Time of day to remind about tasks due on a date without a time. (HH:MM)
+    #[serde(default = "default_all_day_time")]
+    pub all_day_time: String,
+    /// Shell command run for each reminder, with the message as $1.
+    #[serde(default)]
+    pub command: Option<String>,
+    /// File to append reminders to.
+    #[serde(default)]
+    pub log: Option<String>,
+    /// Unix socket to write reminders to.
+    #[serde(default)]
+    pub socket: Option<String>,
+    /// Seconds between checks.
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+}
+
+impl Default for Reminders {
+    fn default() -> Reminders {
+        Reminders {
+            lead: None,
+            all_day_time: default_all_day_time(),
+            command: None,
+            log: None,
+            socket: None,
+            interval: default_interval(),
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Config {
@@ -9,33 +125,220 @@ pub struct Config {
     pub time_24_hour: bool,
     pub date_numerical: bool,
 
-    pub error_color: String,
-    pub flag_color: String,
-    pub success_color: String,
-    pub default_color: String,
-    pub complete_color: String,
-    pub incomplete_color: String,
+    /// Built-in theme the colors below are layered on. (dark or light)
+    #[serde(default = "default_theme")]
+    pub theme: String,
+    pub error_color: Option<String>,
+    pub flag_color: Option<String>,
+    pub success_color: Option<String>,
+    pub default_color: Option<String>,
+    pub complete_color: Option<String>,
+    pub incomplete_color: Option<String>,
+    pub in_progress_color: Option<String>,
+    pub blocked_color: Option<String>,
+    pub waiting_color: Option<String>,
+    pub cancelled_color: Option<String>,
+    #[serde(default)]
+    pub highlight_color: Option<String>,
+
+    #[serde(default)]
+    pub urgency: Urgency,
+    /// Custom task attributes, keyed by name.
+    #[serde(default)]
+    pub attributes: Attributes,
+    /// Named filter expressions, e.g. "errands": "@town incomplete".
+    #[serde(default)]
+    pub contexts: BTreeMap<String, String>,
+    /// The context 'list' and 'search' are limited to, set with 'context set'.
+    #[serde(default)]
+    pub context: Option<String>,
+    #[serde(default)]
+    pub reminders: Reminders,
+    /// Directory of the on-launch, on-add, on-modify, on-complete and on-delete hook scripts.
+    #[serde(default = "default_hooks_dir")]
+    pub hooks_dir: String,
+    /// Commands changing more tasks than this list them and ask before going ahead.
+    #[serde(default = "default_confirm_threshold")]
+    pub confirm_threshold: usize,
+    /// Where 'archive' moves old closed tasks to.
+    #[serde(default = "default_archive_file")]
+    pub archive_file: String,
+    /// Data files of the named task lists besides the default one in `data_file`, set up with 'use'.
+    #[serde(default)]
+    pub lists: BTreeMap<String, String>,
+    /// The list commands work on, set with 'use'. The default list when unset.
+    #[serde(default)]
+    pub list: Option<String>,
+    /// The list picked with '--list' for a single run. Never saved.
+    #[serde(skip)]
+    pub list_override: Option<String>,
+    /// Names standing for longer command lines, e.g. "today": "list due:today --sort due".
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+    /// Reports run with 'report NAME', keyed by name.
+    #[serde(default)]
+    pub reports: BTreeMap<String, SavedReport>,
+    /// Where the config was read from and any data file given on the command line. Never saved.
+    #[serde(skip)]
+    pub(crate) paths: Paths,
 }
 
+/// Name of the list kept in `data_file`.
+pub(crate) const DEFAULT_LIST: &str = "default";
+
 impl Config {
     pub fn default() -> Config {
         Config {
-            data_file: String::from("data/tasks.json"),
+            data_file: default_data_file(),
             time_24_hour: false,
             date_numerical: false,
 
-            error_color: String::from("\x1b[31m"),
-            flag_color: String::from("\x1b[4m"),
-            success_color: String::from("\x1b[32m"),
-            default_color: String::from("\x1b[0m"),
-            complete_color: String::from("\x1b[36m"),
-            incomplete_color: String::from("\x1b[31m"),
+            theme: default_theme(),
+            error_color: None,
+            flag_color: None,
+            success_color: None,
+            default_color: None,
+            complete_color: None,
+            incomplete_color: None,
+            in_progress_color: None,
+            blocked_color: None,
+            waiting_color: None,
+            cancelled_color: None,
+            highlight_color: None,
+
+            urgency: Urgency::default(),
+            attributes: Attributes::new(),
+            contexts: BTreeMap::new(),
+            context: None,
+            reminders: Reminders::default(),
+            hooks_dir: default_hooks_dir(),
+            confirm_threshold: default_confirm_threshold(),
+            archive_file: default_archive_file(),
+            lists: BTreeMap::new(),
+            list: None,
+            list_override: None,
+            aliases: BTreeMap::new(),
+            reports: BTreeMap::new(),
+            paths: Paths::default(),
+        }
+    }
+
+    /// Resolves the configured theme and color overrides, reporting the first invalid value.
+    pub fn check_theme(&self) -> Result<Theme, ThemeError> {
+        let mut theme = Theme::by_name(&self.theme)?;
+
+        let overrides = [
+            (&mut theme.error, &self.error_color),
+            (&mut theme.flag, &self.flag_color),
+            (&mut theme.success, &self.success_color),
+            (&mut theme.default, &self.default_color),
+            (&mut theme.complete, &self.complete_color),
+            (&mut theme.incomplete, &self.incomplete_color),
+            (&mut theme.in_progress, &self.in_progress_color),
+            (&mut theme.blocked, &self.blocked_color),
+            (&mut theme.waiting, &self.waiting_color),
+            (&mut theme.cancelled, &self.cancelled_color),
+            (&mut theme.highlight, &self.highlight_color),
+        ];
+
+        for (style, color) in overrides {
+            if let Some(color) = color {
+                *style = Style::parse(color)?;
+            }
+        }
+
+        Ok(theme)
+    }
+
+    /// The theme to render with. Falls back to the dark theme on invalid values and
+    /// to no styling at all when colors are disabled.
+    pub fn theme(&self) -> Theme {
+        if !colors_enabled() {
+            return Theme::plain();
+        }
+
+        self.check_theme().unwrap_or_else(|_| Theme::dark())
+    }
+
+    /// Filters of the active context, empty when no context is set.
+    pub fn context_filters(&self) -> Result<Vec<String>, String> {
+        match &self.context {
+            Some(name) => match self.contexts.get(name) {
+                Some(expression) => Ok(expression.split_whitespace().map(|filter| filter.to_string()).collect()),
+                None => Err(format!("Context '{}' is not defined. Run 'taskninja context clear' or define it again.", name)),
+            },
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Name of the list commands work on: the one given with '--list', else the one picked with 'use'.
+    pub fn current_list(&self) -> String {
+        self.list_override.clone()
+            .or_else(|| self.list.clone())
+            .unwrap_or_else(|| DEFAULT_LIST.to_string())
+    }
+
+    /// Every list name, the default list first.
+    pub fn list_names(&self) -> Vec<String> {
+        let mut names = vec![DEFAULT_LIST.to_string()];
+        names.extend(self.lists.keys().cloned());
+        names
+    }
+
+    /// Data file of the list called `name`, if there is one.
+    pub fn list_file(&self, name: &str) -> Option<String> {
+        match name {
+            DEFAULT_LIST => Some(self.data_file.clone()),
+            _ => self.lists.get(name).cloned(),
+        }
+    }
+
+    /// Data file of the current list, unless '--data-file' or `TASKNINJA_DATA` gave another one.
+    /// Falls back to `data_file` when the list is gone from the config.
+    pub fn tasks_file(&self) -> String {
+        if let Some(data) = &self.paths.data {
+            return data.path.clone();
+        }
+        self.list_file(&self.current_list()).unwrap_or_else(|| self.data_file.clone())
+    }
+
+    /// This config switched to list `name` for the run, as '--list' does. The '--data-file' or
+    /// `TASKNINJA_DATA` override stays with the current list, so other lists use their own files.
+    pub fn with_list(&self, name: &str) -> Result<Config, String> {
+        if self.list_file(name).is_none() {
+            return Err(format!("Unknown list '{}'. Lists: {}.", name, self.list_names().join(", ")));
+        }
+
+        let mut config = self.clone();
+        if name != self.current_list() {
+            config.paths.data = None;
         }
+        config.list_override = Some(name.to_string());
+        Ok(config)
     }
 
+    /// Adds a list whose tasks are kept in `lists/NAME.json` next to `data_file`.
+    pub fn add_list(&mut self, name: &str) -> Result<String, String> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_') {
+            return Err(format!("'{}' is not a valid list name. Use lowercase letters, digits, '-' and '_'.", name));
+        }
+
+        let file = Path::new(&self.data_file)
+            .with_file_name("lists")
+            .join(format!("{}.json", name))
+            .to_string_lossy()
+            .to_string();
+        self.lists.insert(name.to_string(), file.clone());
+        Ok(file)
+    }
+
+    /// Writes the config back to the file it was read from.
     pub fn save_to_file(&self) -> Result<String, SaveError> {
-        std::fs::create_dir("data").unwrap_or_default();
-        let file = File::create("data/config.json").unwrap();
+        let path = Path::new(&self.paths.config.path);
+        if let Some(directory) = path.parent().filter(|directory| !directory.as_os_str().is_empty()) {
+            std::fs::create_dir_all(directory).unwrap_or_default();
+        }
+        let file = File::create(path).map_err(|error| SaveError::FailedSave(format!("{}: {}", path.display(), error)))?;
         let write = to_writer_pretty(file, &self);
 
         match write {
@@ -45,18 +348,303 @@ impl Config {
     }
 
 
+    /// Reads the config at `path`. Settings missing from the file take their default values.
     pub fn read_from_file(path: String) -> Result<Config, SaveError> {
         let file = File::open(&path);
 
         match file {
             Ok(file) => {
-                let config = serde_json::from_reader(file);
-                match config {
-                    Ok(config) => Ok(config),
+                let value: Result<Value, _> = serde_json::from_reader(file);
+                match value {
+                    Ok(value) => {
+                        let mut merged = Config::default().to_value();
+                        merge(&mut merged, value);
+                        serde_json::from_value(merged).map_err(|error| SaveError::FailedRead(error.to_string()))
+                    }
                     Err(error) => Err(SaveError::FailedRead(error.to_string())),
                 }
             }
             Err(_) => Err(SaveError::FailedRead("Error reading config file, using default config.".to_string())),
         }
     }
-}
\ No newline at end of file
+
+    /// Reads the config file again, keeping what was picked at startup.
+    pub fn reload(&self) -> Result<Config, SaveError> {
+        let mut config = Config::read_from_file(self.paths.config.path.clone())?;
+        config.list_override = self.list_override.clone();
+        config.paths = self.paths.clone();
+        Ok(config)
+    }
+
+    fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+
+    /// Rebuilds the config from `value`, keeping what was picked at startup.
+    fn with_value(&self, value: Value) -> Result<Config, String> {
+        let mut config: Config = serde_json::from_value(value).map_err(|error| error.to_string())?;
+        config.list_override = self.list_override.clone();
+        config.paths = self.paths.clone();
+        Ok(config)
+    }
+
+    /// Reports the first setting that doesn't work with the others.
+    pub fn check(&self) -> Result<(), String> {
+        self.check_theme().map_err(|error| error.to_string())?;
+        check_attributes(&self.attributes)?;
+        check_aliases(&self.aliases)?;
+        check_reports(&self.reports, &self.attributes)?;
+        self.context_filters()?;
+        if let Some(list) = &self.list {
+            self.with_list(list)?;
+        }
+        Ok(())
+    }
+
+    /// Every setting as a dotted key and its value, e.g. `urgency.due`.
+    pub fn settings(&self) -> Vec<(String, Value)> {
+        let mut settings = Vec::new();
+        flatten("", self.to_value(), &mut settings);
+        settings
+    }
+
+    /// The settings under `key`, a single one unless it names a group like `urgency`.
+    pub fn get(&self, key: &str) -> Result<Vec<(String, Value)>, String> {
+        let value = self.to_value().pointer(&pointer(key)).cloned().ok_or_else(|| self.unknown(key))?;
+        let mut settings = Vec::new();
+        flatten(key, value, &mut settings);
+        Ok(settings)
+    }
+
+    /// Sets `key` to `raw`, read as the type the setting has. Groups and lists of values take JSON.
+    pub fn set(&mut self, key: &str, raw: &str) -> Result<(), String> {
+        let mut value = self.to_value();
+        let (group, name) = key.rsplit_once('.').unwrap_or(("", key));
+        let parent = value.pointer_mut(&pointer(group))
+            .and_then(|parent| parent.as_object_mut())
+            .ok_or_else(|| self.unknown(key))?;
+
+        let new = match parent.get(name) {
+            Some(Value::Bool(_)) => match raw.to_lowercase().as_str() {
+                "true" | "yes" | "on" => Value::Bool(true),
+                "false" | "no" | "off" => Value::Bool(false),
+                _ => return Err(format!("'{}' takes true or false, not '{}'.", key, raw)),
+            },
+            Some(Value::Number(number)) if number.is_u64() => raw.parse::<u64>()
+                .map(Value::from)
+                .map_err(|_| format!("'{}' takes a whole number, not '{}'.", key, raw))?,
+            Some(Value::Number(_)) => raw.parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number)
+                .ok_or_else(|| format!("'{}' takes a number, not '{}'.", key, raw))?,
+            Some(Value::String(_)) | Some(Value::Null) => match raw {
+                "" | "none" | "null" => Value::Null,
+                _ => Value::String(raw.to_string()),
+            },
+            Some(Value::Object(_)) | Some(Value::Array(_)) => serde_json::from_str(raw)
+                .map_err(|error| format!("'{}' takes JSON: {}", key, error))?,
+            None if MAP_KEYS.contains(&group) => serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string())),
+            None => return Err(self.unknown(key)),
+        };
+        parent.insert(name.to_string(), new);
+
+        let config = self.with_value(value).map_err(|error| format!("'{}': {}", key, error))?;
+        config.check()?;
+        *self = config;
+        Ok(())
+    }
+
+    /// Puts `key` back to its default, removing it from maps like `contexts`. Without a key, resets
+    /// every setting but those saying where tasks are kept.
+    pub fn reset(&mut self, key: Option<&str>) -> Result<(), String> {
+        let defaults = Config::default().to_value();
+        let mut value = self.to_value();
+
+        match key {
+            Some(key) => {
+                let (group, name) = key.rsplit_once('.').unwrap_or(("", key));
+                let parent = value.pointer_mut(&pointer(group))
+                    .and_then(|parent| parent.as_object_mut())
+                    .filter(|parent| parent.contains_key(name))
+                    .ok_or_else(|| self.unknown(key))?;
+                match defaults.pointer(&pointer(key)) {
+                    Some(default) => parent.insert(name.to_string(), default.clone()),
+                    None => parent.remove(name),
+                };
+            }
+            None => {
+                let mut reset = defaults;
+                for kept in KEPT_ON_RESET {
+                    reset[kept] = value[kept].take();
+                }
+                value = reset;
+            }
+        }
+
+        let config = self.with_value(value)?;
+        config.check()?;
+        *self = config;
+        Ok(())
+    }
+
+    /// Names the closest known setting, if any is close.
+    fn unknown(&self, key: &str) -> String {
+        let keys: Vec<String> = self.settings().into_iter().map(|(key, _)| key).collect();
+        let closest = keys.iter()
+            .map(|known| (distance(key, known), known))
+            .filter(|(distance, _)| *distance <= 3)
+            .min();
+
+        match closest {
+            Some((_, known)) => format!("Unknown setting '{}'. Did you mean '{}'?", key, known),
+            None => format!("Unknown setting '{}'. Run 'taskninja config list' for every setting.", key),
+        }
+    }
+}
+
+/// Settings holding names picked by the user, which 'config set' may add keys to.
+const MAP_KEYS: [&str; 6] = ["aliases", "attributes", "contexts", "lists", "reports", "urgency.tag"];
+
+/// Settings 'config reset' leaves alone, so tasks stay where they are.
+const KEPT_ON_RESET: [&str; 4] = ["data_file", "archive_file", "lists", "list"];
+
+/// A JSON pointer to dotted `key`.
+fn pointer(key: &str) -> String {
+    match key {
+        "" => String::new(),
+        _ => format!("/{}", key.replace('.', "/")),
+    }
+}
+
+/// Overlays `value` on `base`, key by key within objects.
+fn merge(base: &mut Value, value: Value) {
+    match (base, value) {
+        (Value::Object(base), Value::Object(value)) => {
+            for (key, value) in value {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, value) => *base = value,
+    }
+}
+
+fn flatten(prefix: &str, value: Value, settings: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Object(object) if !object.is_empty() => {
+            for (key, value) in object {
+                let key = if prefix.is_empty() { key } else { format!("{}.{}", prefix, key) };
+                flatten(&key, value, settings);
+            }
+        }
+        value => settings.push((prefix.to_string(), value)),
+    }
+}
+
+/// Edit distance between two keys, to suggest one on typos.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let cost = if a == *b { 0 } else { 1 };
+            current.push((previous[j] + cost).min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use super::*;
+
+    #[test]
+    fn set_reads_values_as_the_setting_type() {
+        let mut config = Config::default();
+        config.set("time_24_hour", "yes").unwrap();
+        config.set("confirm_threshold", "10").unwrap();
+        config.set("urgency.due", "4.5").unwrap();
+        config.set("reminders.lead", "15m").unwrap();
+        config.set("contexts.work", "+work incomplete").unwrap();
+
+        assert!(config.time_24_hour);
+        assert_eq!(config.confirm_threshold, 10);
+        assert_eq!(config.urgency.due, 4.5);
+        assert_eq!(config.reminders.lead.as_deref(), Some("15m"));
+        assert_eq!(config.contexts.get("work").map(String::as_str), Some("+work incomplete"));
+    }
+
+    #[test]
+    fn set_rejects_values_of_the_wrong_type() {
+        let mut config = Config::default();
+        assert!(config.set("time_24_hour", "maybe").is_err());
+        assert!(config.set("confirm_threshold", "-1").is_err());
+        assert!(config.set("urgency.due", "high").is_err());
+        assert!(config.set("theme", "neon").is_err());
+        assert_eq!(config.theme, default_theme());
+    }
+
+    #[test]
+    fn unknown_keys_suggest_the_closest_setting() {
+        let mut config = Config::default();
+        assert_eq!(config.set("time_24_hours", "true"), Err("Unknown setting 'time_24_hours'. Did you mean 'time_24_hour'?".to_string()));
+        assert_eq!(config.get("urgncy.due").unwrap_err(), "Unknown setting 'urgncy.due'. Did you mean 'urgency.due'?");
+        assert_eq!(config.reset(Some("nothing.like.this")).unwrap_err(), "Unknown setting 'nothing.like.this'. Run 'taskninja config list' for every setting.");
+    }
+
+    #[test]
+    fn reset_restores_defaults_but_keeps_where_tasks_are() {
+        let mut config = Config::default();
+        config.set("time_24_hour", "true").unwrap();
+        config.set("contexts.work", "+work").unwrap();
+        config.set("data_file", "/tmp/tasks.json").unwrap();
+
+        config.reset(Some("contexts.work")).unwrap();
+        assert!(config.contexts.is_empty());
+        assert!(config.time_24_hour);
+
+        config.reset(None).unwrap();
+        assert!(!config.time_24_hour);
+        assert_eq!(config.data_file, "/tmp/tasks.json");
+    }
+
+    #[test]
+    fn merge_overlays_objects_key_by_key() {
+        let mut base = json!({ "theme": "dark", "urgency": { "due": 12.0, "flagged": 5.0 }, "contexts": {} });
+        merge(&mut base, json!({ "urgency": { "due": 3.0 }, "contexts": { "home": "@home" } }));
+        assert_eq!(base, json!({ "theme": "dark", "urgency": { "due": 3.0, "flagged": 5.0 }, "contexts": { "home": "@home" } }));
+    }
+
+    #[test]
+    fn missing_settings_take_their_defaults() {
+        let path = std::env::temp_dir().join(format!("taskninja-config-test-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{ "data_file": "/tmp/tasks.json", "time_24_hour": true, "urgency": { "due": 3.0 } }"#).unwrap();
+        let config = Config::read_from_file(path.to_string_lossy().to_string());
+        let _ = std::fs::remove_file(&path);
+
+        let config = config.unwrap();
+        assert_eq!(config.data_file, "/tmp/tasks.json");
+        assert!(config.time_24_hour);
+        assert_eq!(config.urgency.due, 3.0);
+        assert_eq!(config.urgency.flagged, Urgency::default().flagged);
+        assert_eq!(config.theme, default_theme());
+        assert_eq!(config.confirm_threshold, default_confirm_threshold());
+    }
+
+    #[test]
+    fn distance_counts_single_character_edits() {
+        assert_eq!(distance("theme", "theme"), 0);
+        assert_eq!(distance("them", "theme"), 1);
+        assert_eq!(distance("thmee", "theme"), 2);
+        assert_eq!(distance("", "abc"), 3);
+    }
+}
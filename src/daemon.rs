@@ -0,0 +1,335 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::process::Command;
+use std::time::SystemTime;
+use chrono::{DateTime, Duration, Local, NaiveTime, TimeZone};
+use crate::{Config, Time, TaskList};
+use crate::config::Reminders;
+use crate::dates::parse_duration;
+use crate::utils::read_tasks;
+
+/// A reminder about a task, sent at `at`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Reminder {
+    pub(crate) num: u8,
+    pub(crate) title: String,
+    pub(crate) due: DateTime<Local>,
+    pub(crate) at: DateTime<Local>,
+}
+
+impl Reminder {
+    pub(crate) fn message(&self) -> String {
+        format!("'{}' is due at {}.", self.title, self.due.format("%Y-%m-%d %H:%M"))
+    }
+}
+
+/// Where the current time comes from, so the daemon can run against a fake clock.
+pub(crate) trait Clock {
+    fn now(&self) -> DateTime<Local>;
+    fn sleep(&self, duration: std::time::Duration);
+}
+
+struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+
+    fn sleep(&self, duration: std::time::Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// Delivers reminders.
+pub(crate) trait Notifier {
+    fn notify(&mut self, reminder: &Reminder) -> Result<(), String>;
+}
+
+struct Stdout;
+
+impl Notifier for Stdout {
+    fn notify(&mut self, reminder: &Reminder) -> Result<(), String> {
+        println!("{}  Reminder: {}", reminder.at.format("%Y-%m-%d %H:%M"), reminder.message());
+        Ok(())
+    }
+}
+
+/// Runs a shell command with the message as `$1` and the task in `TASKNINJA_*` variables.
+struct ShellCommand(String);
+
+impl Notifier for ShellCommand {
+    fn notify(&mut self, reminder: &Reminder) -> Result<(), String> {
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(&self.0)
+            .arg("sh")
+            .arg(reminder.message())
+            .env("TASKNINJA_ID", reminder.num.to_string())
+            .env("TASKNINJA_TITLE", &reminder.title)
+            .env("TASKNINJA_DUE", reminder.due.format("%Y-%m-%d %H:%M").to_string())
+            .status()
+            .map_err(|error| format!("Could not run '{}': {}", self.0, error))?;
+
+        match status.success() {
+            true => Ok(()),
+            false => Err(format!("'{}' exited with {}.", self.0, status)),
+        }
+    }
+}
+
+/// Appends a line per reminder to a file.
+struct Log(String);
+
+impl Notifier for Log {
+    fn notify(&mut self, reminder: &Reminder) -> Result<(), String> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.0).map_err(|error| format!("Could not open {}: {}", self.0, error))?;
+        writeln!(file, "{}  Reminder: {}", reminder.at.format("%Y-%m-%d %H:%M"), reminder.message()).map_err(|error| format!("Could not write {}: {}", self.0, error))
+    }
+}
+
+/// Writes a line per reminder to a Unix socket.
+struct Socket(String);
+
+impl Notifier for Socket {
+    #[cfg(unix)]
+    fn notify(&mut self, reminder: &Reminder) -> Result<(), String> {
+        let mut stream = std::os::unix::net::UnixStream::connect(&self.0).map_err(|error| format!("Could not connect to {}: {}", self.0, error))?;
+        writeln!(stream, "Reminder: {}", reminder.message()).map_err(|error| format!("Could not write to {}: {}", self.0, error))
+    }
+
+    #[cfg(not(unix))]
+    fn notify(&mut self, _reminder: &Reminder) -> Result<(), String> {
+        Err("Sockets are only supported on Unix.".to_string())
+    }
+}
+
+/// Every notifier set under `reminders` in the config, or stdout when there are none.
+fn notifiers(reminders: &Reminders) -> Vec<Box<dyn Notifier>> {
+    let mut notifiers: Vec<Box<dyn Notifier>> = Vec::new();
+    if let Some(command) = &reminders.command {
+        notifiers.push(Box::new(ShellCommand(command.clone())));
+    }
+    if let Some(log) = &reminders.log {
+        notifiers.push(Box::new(Log(log.clone())));
+    }
+    if let Some(socket) = &reminders.socket {
+        notifiers.push(Box::new(Socket(socket.clone())));
+    }
+    if notifiers.is_empty() {
+        notifiers.push(Box::new(Stdout));
+    }
+    notifiers
+}
+
+/// When reminders are due, from the `reminders` settings.
+pub(crate) struct Schedule {
+    /// Lead time of tasks without their own.
+    lead: Duration,
+    /// Time of day to remind about tasks due on a date without a time.
+    all_day_time: NaiveTime,
+}
+
+impl Schedule {
+    pub(crate) fn from_config(reminders: &Reminders) -> Result<Schedule, String> {
+        let lead = match &reminders.lead {
+            Some(lead) => parse_duration(lead).map_err(|error| format!("reminders.lead: {}", error))?,
+            None => Duration::zero(),
+        };
+        let all_day_time = Time::parse(&reminders.all_day_time)
+            .ok()
+            .and_then(|time| time.to_naive_time())
+            .ok_or_else(|| format!("reminders.all_day_time: '{}' is not a time. Use HH:MM.", reminders.all_day_time))?;
+
+        Ok(Schedule { lead, all_day_time })
+    }
+
+    /// Reminders of every open task with a due date, `--remind` (or the default lead) before it's due.
+    pub(crate) fn reminders(&self, task_list: &TaskList) -> Vec<Reminder> {
+        task_list.tasks.iter()
+            .filter(|task| task.status.is_open())
+            .filter_map(|task| {
+                let date = task.due_date.as_ref().ok()?.to_naive_date()?;
+                let time = match &task.due_time {
+                    Ok(time) => time.to_naive_time()?,
+                    Err(_) => self.all_day_time,
+                };
+                let due = Local.from_local_datetime(&date.and_time(time)).earliest()?;
+                let lead = task.remind().unwrap_or(self.lead);
+                Some(Reminder { num: task.num, title: task.title.clone(), due, at: due - lead })
+            })
+            .collect()
+    }
+}
+
+/// Sends each reminder once, on the first check after its time.
+pub(crate) struct Scheduler {
+    schedule: Schedule,
+    last_check: DateTime<Local>,
+}
+
+impl Scheduler {
+    /// Reminders due before `start` are not sent.
+    pub(crate) fn new(schedule: Schedule, start: DateTime<Local>) -> Scheduler {
+        Scheduler { schedule, last_check: start }
+    }
+
+    /// Sends the reminders that came due since the last check. Returns the delivery errors.
+    pub(crate) fn check(&mut self, task_list: &TaskList, now: DateTime<Local>, notifiers: &mut [Box<dyn Notifier>]) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        for reminder in self.schedule.reminders(task_list).iter().filter(|reminder| self.last_check < reminder.at && reminder.at <= now) {
+            for notifier in notifiers.iter_mut() {
+                if let Err(error) = notifier.notify(reminder) {
+                    errors.push(error);
+                }
+            }
+        }
+
+        self.last_check = now;
+        errors
+    }
+}
+
+/// Watches the data file and sends reminders until interrupted. The tasks are read again whenever
+/// the file changes.
+pub(crate) fn run(config: &Config, clock: &dyn Clock, notifiers: &mut [Box<dyn Notifier>]) -> Result<String, String> {
+    let schedule = Schedule::from_config(&config.reminders)?;
+    let interval = std::time::Duration::from_secs(config.reminders.interval.max(1));
+    let mut scheduler = Scheduler::new(schedule, clock.now());
+    let mut task_list = TaskList::new();
+    let mut modified: Option<SystemTime> = None;
+
+    let data_file = config.tasks_file();
+    println!("Watching {} for reminders. Press Ctrl-C to stop.", data_file);
+
+    loop {
+        let current = fs::metadata(&data_file).and_then(|metadata| metadata.modified()).ok();
+        if current != modified || modified.is_none() {
+            task_list = read_tasks(config.clone()).unwrap_or_else(|_| TaskList::new());
+            modified = current;
+        }
+
+        for error in scheduler.check(&task_list, clock.now(), notifiers) {
+            eprintln!("Reminder error: {}", error);
+        }
+
+        clock.sleep(interval);
+    }
+}
+
+/// Runs the daemon with the system clock and the notifiers from the config.
+pub(crate) fn start(config: &Config) -> Result<String, String> {
+    run(config, &SystemClock, &mut notifiers(&config.reminders))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use crate::Date;
+    use crate::tasks::{Status, Task};
+    use super::*;
+
+    /// A clock that only moves when the daemon sleeps.
+    struct FakeClock(Cell<DateTime<Local>>);
+
+    impl Clock for FakeClock {
+        fn now(&self) -> DateTime<Local> {
+            self.0.get()
+        }
+
+        fn sleep(&self, duration: std::time::Duration) {
+            self.0.set(self.0.get() + Duration::from_std(duration).unwrap());
+        }
+    }
+
+    /// Keeps every reminder it's given.
+    struct Recorder(Rc<RefCell<Vec<Reminder>>>);
+
+    impl Notifier for Recorder {
+        fn notify(&mut self, reminder: &Reminder) -> Result<(), String> {
+            self.0.borrow_mut().push(reminder.clone());
+            Ok(())
+        }
+    }
+
+    fn at(time: &str) -> DateTime<Local> {
+        Local.from_local_datetime(&chrono::NaiveDateTime::parse_from_str(&format!("2030-06-15 {}", time), "%Y-%m-%d %H:%M").unwrap()).unwrap()
+    }
+
+    fn task(title: &str, due_time: Option<&str>, remind_minutes: Option<i64>, status: Status) -> Task {
+        let mut task = Task::from(title.to_string(), None, Some(Date::parse("2030-06-15")), due_time.map(Time::parse), None, None, None, status, false);
+        task.remind_minutes = remind_minutes;
+        task
+    }
+
+    fn task_list(tasks: Vec<Task>) -> TaskList {
+        let mut task_list = TaskList::new();
+        for task in tasks {
+            task_list.add_task(task);
+        }
+        task_list
+    }
+
+    fn schedule(lead: Option<&str>) -> Schedule {
+        let reminders = Reminders { lead: lead.map(str::to_string), ..Reminders::default() };
+        Schedule::from_config(&reminders).unwrap()
+    }
+
+    /// Checks once a minute from `start` until `end`, and returns what was sent as (title, time sent).
+    fn run_checks(schedule: Schedule, task_list: &TaskList, start: &str, end: &str) -> Vec<(String, DateTime<Local>)> {
+        let clock = FakeClock(Cell::new(at(start)));
+        let sent = Rc::new(RefCell::new(Vec::new()));
+        let mut notifiers: Vec<Box<dyn Notifier>> = vec![Box::new(Recorder(sent.clone()))];
+        let mut scheduler = Scheduler::new(schedule, clock.now());
+        let mut received = Vec::new();
+
+        while clock.now() < at(end) {
+            clock.sleep(std::time::Duration::from_secs(60));
+            let before = sent.borrow().len();
+            assert!(scheduler.check(task_list, clock.now(), &mut notifiers).is_empty());
+            received.extend(sent.borrow()[before..].iter().map(|reminder| (reminder.title.clone(), clock.now())));
+        }
+        received
+    }
+
+    #[test]
+    fn reminders_due_before_start_are_not_sent() {
+        let tasks = task_list(vec![task("Early", Some("08:00"), None, Status::Todo), task("Late", Some("11:00"), None, Status::Todo)]);
+        let sent = run_checks(schedule(None), &tasks, "10:00", "12:00");
+        assert_eq!(sent, vec![("Late".to_string(), at("11:00"))]);
+    }
+
+    #[test]
+    fn each_reminder_is_sent_once() {
+        let tasks = task_list(vec![task("Call", Some("10:30"), None, Status::Todo)]);
+        let sent = run_checks(schedule(None), &tasks, "10:00", "23:00");
+        assert_eq!(sent, vec![("Call".to_string(), at("10:30"))]);
+    }
+
+    #[test]
+    fn task_lead_comes_before_configured_lead() {
+        let tasks = task_list(vec![task("Own lead", Some("12:00"), Some(60), Status::Todo), task("Default lead", Some("12:00"), None, Status::Todo)]);
+        let sent = run_checks(schedule(Some("15m")), &tasks, "10:00", "13:00");
+        assert_eq!(sent, vec![("Own lead".to_string(), at("11:00")), ("Default lead".to_string(), at("11:45"))]);
+    }
+
+    #[test]
+    fn tasks_without_due_time_use_all_day_time() {
+        let tasks = task_list(vec![task("All day", None, None, Status::Todo)]);
+        let sent = run_checks(schedule(None), &tasks, "08:00", "12:00");
+        assert_eq!(sent, vec![("All day".to_string(), at("09:00"))]);
+    }
+
+    #[test]
+    fn closed_tasks_are_skipped() {
+        let tasks = task_list(vec![
+            task("Done", Some("10:30"), None, Status::Done),
+            task("Cancelled", Some("10:30"), None, Status::Cancelled),
+            task("Open", Some("10:30"), None, Status::Todo),
+        ]);
+        let sent = run_checks(schedule(None), &tasks, "10:00", "11:00");
+        assert_eq!(sent, vec![("Open".to_string(), at("10:30"))]);
+    }
+}
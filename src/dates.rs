@@ -2,7 +2,7 @@ use std::collections::HashMap;
 use lazy_static::lazy_static;
 use std::fmt;
 use std::error::Error as StdError;
-use chrono::Local;
+use chrono::{Duration, Local, NaiveDate, NaiveTime};
 use serde::{Serialize, Deserialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -14,18 +14,22 @@ pub enum DateTimeError {
     InvalidMinute,
     UnspecifiedDate,
     UnspecifiedTime,
+    InvalidDuration,
+    DurationOutOfBounds,
 }
 
 impl fmt::Display for DateTimeError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             DateTimeError::InvalidYear => write!(f, "Year out of bounds."),
-            DateTimeError::InvalidMonth => write!(f, "Month out of bounds.m"),
+            DateTimeError::InvalidMonth => write!(f, "Month out of bounds."),
             DateTimeError::InvalidDay => write!(f, "Day out of bounds."),
             DateTimeError::InvalidHour => write!(f, "Hour out of bounds."),
             DateTimeError::InvalidMinute => write!(f, "Minute out of bounds."),
             DateTimeError::UnspecifiedDate => write!(f, "Date not specified."),
             DateTimeError::UnspecifiedTime => write!(f, "Time not specified."),
+            DateTimeError::InvalidDuration => write!(f, "Not a duration. Use e.g. 1h30m, 2h or 45m."),
+            DateTimeError::DurationOutOfBounds => write!(f, "Duration out of bounds."),
         }
     }
 }
@@ -40,6 +44,8 @@ impl StdError for DateTimeError {
             DateTimeError::InvalidMinute => "Minute out of bounds.",
             DateTimeError::UnspecifiedDate => "Date not specified.",
             DateTimeError::UnspecifiedTime => "Time not specified.",
+            DateTimeError::InvalidDuration => "Not a duration.",
+            DateTimeError::DurationOutOfBounds => "Duration out of bounds.",
         }
     }
 }
@@ -149,10 +155,10 @@ impl Date {
         let month = month.to_string();
         match MONTHS_EXPAND.get(month.to_lowercase().as_str()) {
             Some(month_name) => {
-                if day < 1 || day > DAY_LIMITS.get(month_name).unwrap().to_owned() as u8 {
+                if day < 1 || day > *DAY_LIMITS.get(month_name).unwrap() {
                     Err(DateTimeError::InvalidDay)
                 } else {
-                    let month_num = MONTH_TO_DAY.get(month_name).unwrap().to_owned() as u8;
+                    let month_num = MONTH_TO_DAY.get(month_name).unwrap().to_owned();
                     let month = Month {
                         month_name: month_name.to_string(),
                         month_num,
@@ -176,9 +182,9 @@ impl Date {
             return Err(DateTimeError::UnspecifiedDate);
         }
 
-        let year = date[0].parse::<u32>().expect("Invalid year.");
+        let year = date[0].parse::<u32>().map_err(|_| DateTimeError::InvalidYear)?;
         let month = date[1];
-        let day = date[2].parse::<u8>().expect("Invalid day.");
+        let day = date[2].parse::<u8>().map_err(|_| DateTimeError::InvalidDay)?;
 
         Date::new(year, month, day)
     }
@@ -196,6 +202,20 @@ impl Date {
     pub fn as_numerical_date_string(&self) -> String {
         format!("{} {}, {}", self.month.month_num, self.day, self.year)
     }
+
+    /// Year, month and day, for ordering dates.
+    pub fn sort_key(&self) -> (u32, u8, u8) {
+        (self.year, self.month.month_num, self.day)
+    }
+
+    pub fn to_naive_date(&self) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(self.year as i32, self.month.month_num as u32, self.day as u32)
+    }
+
+    /// Formats the date as YYYY-MM-DD, which `Date::parse` accepts.
+    pub fn as_iso_date_string(&self) -> String {
+        format!("{}-{:02}-{:02}", self.year, self.month.month_num, self.day)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -225,12 +245,21 @@ impl Time {
             return Err(DateTimeError::UnspecifiedTime);
         }
 
-        let hour = time[0].parse::<u8>().expect("Invalid hour.");
-        let minute = time[1].parse::<u8>().expect("Invalid minute.");
+        let hour = time[0].parse::<u8>().map_err(|_| DateTimeError::InvalidHour)?;
+        let minute = time[1].parse::<u8>().map_err(|_| DateTimeError::InvalidMinute)?;
 
         Time::new(hour, minute)
     }
 
+    pub fn to_naive_time(&self) -> Option<NaiveTime> {
+        NaiveTime::from_hms_opt(self.hour as u32, self.minute as u32, 0)
+    }
+
+    /// Hour and minute, for ordering times.
+    pub fn sort_key(&self) -> (u8, u8) {
+        (self.hour, self.minute)
+    }
+
     pub fn as_24_hour_time_string(&self) -> String {
         let mut hour = self.hour.to_string();
         if hour.len() == 1 {
@@ -260,4 +289,86 @@ impl Time {
         }
         format!("{}:{} {}", hour, minute, am_pm)
     }
-}
\ No newline at end of file
+}
+
+/// Longest duration accepted, about a century, so adding one to a date can't overflow.
+const MAX_DURATION_MINUTES: i64 = 100 * 366 * 24 * 60;
+
+/// Parses a duration like `1h30m`, `2h` or `45m`.
+pub fn parse_duration(duration: &str) -> Result<Duration, DateTimeError> {
+    let mut minutes: i64 = 0;
+    let mut number = String::new();
+
+    for c in duration.to_lowercase().chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'h' | 'm' => {
+                let value = number.parse::<i64>().map_err(|error| match error.kind() {
+                    std::num::IntErrorKind::PosOverflow => DateTimeError::DurationOutOfBounds,
+                    _ => DateTimeError::InvalidDuration,
+                })?;
+                minutes = value.checked_mul(if c == 'h' { 60 } else { 1 })
+                    .and_then(|value| minutes.checked_add(value))
+                    .ok_or(DateTimeError::DurationOutOfBounds)?;
+                number.clear();
+            }
+            _ => return Err(DateTimeError::InvalidDuration),
+        }
+    }
+
+    if !number.is_empty() || minutes == 0 {
+        return Err(DateTimeError::InvalidDuration);
+    }
+    if minutes > MAX_DURATION_MINUTES {
+        return Err(DateTimeError::DurationOutOfBounds);
+    }
+
+    Ok(Duration::minutes(minutes))
+}
+
+/// Formats a duration as hours and minutes, e.g. `1h 30m`.
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes();
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{}m", minutes),
+        (hours, 0) => format!("{}h", hours),
+        (hours, minutes) => format!("{}h {}m", hours, minutes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_add_up_hours_and_minutes() {
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::minutes(90)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::minutes(120)));
+        assert_eq!(parse_duration("45m"), Ok(Duration::minutes(45)));
+        assert_eq!(parse_duration("90M"), Ok(Duration::minutes(90)));
+        assert_eq!(parse_duration("1h1h"), Ok(Duration::minutes(120)));
+    }
+
+    #[test]
+    fn malformed_durations_are_rejected() {
+        for value in ["", "0m", "0h0m", "15", "h", "1x", "1.5h", "-2h", "1h 30m"] {
+            assert_eq!(parse_duration(value), Err(DateTimeError::InvalidDuration), "{}", value);
+        }
+    }
+
+    #[test]
+    fn huge_durations_are_out_of_bounds() {
+        for value in ["99999999999999h", "99999999999999999999h", "153722867280912931m", "878401h"] {
+            assert_eq!(parse_duration(value), Err(DateTimeError::DurationOutOfBounds), "{}", value);
+        }
+        assert_eq!(parse_duration("878400h"), Ok(Duration::minutes(MAX_DURATION_MINUTES)));
+    }
+
+    #[test]
+    fn durations_format_as_hours_and_minutes() {
+        assert_eq!(format_duration(Duration::minutes(45)), "45m");
+        assert_eq!(format_duration(Duration::minutes(120)), "2h");
+        assert_eq!(format_duration(Duration::minutes(90)), "1h 30m");
+        assert_eq!(parse_duration(&format_duration(Duration::minutes(1234)).replace(' ', "")), Ok(Duration::minutes(1234)));
+    }
+}
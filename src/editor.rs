@@ -0,0 +1,73 @@
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The user's editor: `$VISUAL`, then `$EDITOR`, then `vi`.
+fn editor() -> String {
+    ["VISUAL", "EDITOR"].iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+/// Tells apart the temporary files of edits made by the same process.
+static EDITS: AtomicUsize = AtomicUsize::new(0);
+
+/// A temporary file only this process created, removed when dropped.
+struct TempFile(PathBuf);
+
+impl TempFile {
+    /// Creates a new file in the temp directory, never opening one that's already there, e.g. a
+    /// symlink planted by another user.
+    fn create(extension: &str) -> Result<(TempFile, File), String> {
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        let mut attempts = 0;
+        loop {
+            let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.subsec_nanos()).unwrap_or(0);
+            let name = format!("taskninja-{}-{}-{}.{}", std::process::id(), EDITS.fetch_add(1, Ordering::Relaxed), nanos, extension);
+            let path = env::temp_dir().join(name);
+            match options.open(&path) {
+                Ok(file) => return Ok((TempFile(path), file)),
+                Err(error) if error.kind() == ErrorKind::AlreadyExists && attempts < 100 => attempts += 1,
+                Err(error) => return Err(format!("Could not create {}: {}", path.display(), error)),
+            }
+        }
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Opens `text` in the user's editor and returns what was saved. `extension` picks the file
+/// name ending, which most editors use for syntax highlighting.
+pub(crate) fn edit_text(text: &str, extension: &str) -> Result<String, String> {
+    let (temp, mut file) = TempFile::create(extension)?;
+    let path = &temp.0;
+    file.write_all(text.as_bytes()).map_err(|error| format!("Could not write {}: {}", path.display(), error))?;
+    drop(file);
+
+    // Run through the shell so editors configured with arguments, like 'code --wait', work.
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor()))
+        .arg("sh")
+        .arg(path)
+        .status();
+
+    match status {
+        Ok(status) if status.success() => fs::read_to_string(path).map_err(|error| format!("Could not read {}: {}", path.display(), error)),
+        Ok(status) => Err(format!("Editor exited with {}.", status)),
+        Err(error) => Err(format!("Could not start editor '{}': {}", editor(), error)),
+    }
+}
@@ -0,0 +1,261 @@
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use crate::Date;
+use crate::tasks::Task;
+
+/// Dates a date term can test.
+pub(crate) const FIELD_NAMES: [&str; 7] = ["created", "modified", "completed", "due", "scheduled", "wait", "until"];
+
+/// Named days and ranges a date term accepts, besides YYYY-MM-DD and day offsets like -3d or 2w.
+pub(crate) const RANGE_NAMES: [&str; 8] = ["today", "yesterday", "tomorrow", "this_week", "last_week", "next_week", "this_month", "last_month"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Created,
+    Modified,
+    Completed,
+    Due,
+    Scheduled,
+    Wait,
+    Until,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Within,
+    Before,
+    After,
+}
+
+/// Days from `start` up to, but not including, `end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Range {
+    start: NaiveDate,
+    end: NaiveDate,
+}
+
+impl Range {
+    fn day(day: NaiveDate) -> Range {
+        Range { start: day, end: day + Duration::days(1) }
+    }
+
+    fn week_of(day: NaiveDate) -> Range {
+        let start = day - Duration::days(day.weekday().num_days_from_monday() as i64);
+        Range { start, end: start + Duration::days(7) }
+    }
+
+    fn month(year: i32, month: u32) -> Range {
+        let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+        Range {
+            start: NaiveDate::from_ymd_opt(year, month, 1).unwrap(),
+            end: NaiveDate::from_ymd_opt(next_year, next_month, 1).unwrap(),
+        }
+    }
+
+    fn parse(value: &str, today: NaiveDate) -> Result<Range, String> {
+        match value {
+            "today" => return Ok(Range::day(today)),
+            "yesterday" => return Ok(Range::day(today - Duration::days(1))),
+            "tomorrow" => return Ok(Range::day(today + Duration::days(1))),
+            "this_week" => return Ok(Range::week_of(today)),
+            "last_week" => return Ok(Range::week_of(today - Duration::days(7))),
+            "next_week" => return Ok(Range::week_of(today + Duration::days(7))),
+            "this_month" => return Ok(Range::month(today.year(), today.month())),
+            "last_month" => {
+                return Ok(match today.month() {
+                    1 => Range::month(today.year() - 1, 12),
+                    month => Range::month(today.year(), month - 1),
+                });
+            }
+            _ => {}
+        }
+
+        if let Some(days) = parse_offset(value) {
+            return Ok(Range::day(today + Duration::days(days)));
+        }
+
+        match Date::parse(value) {
+            Ok(date) => date.to_naive_date().map(Range::day).ok_or_else(|| format!("'{}' is not a date.", value)),
+            Err(_) => Err(format!("'{}' is not a date. Use YYYY-MM-DD, an offset like -3d or 2w, or one of: {}.", value, RANGE_NAMES.join(", "))),
+        }
+    }
+}
+
+/// Parses a date, also accepting the relative forms date terms use, so 'tomorrow' or '+3d'
+/// work wherever a date is expected.
+pub(crate) fn parse_date(value: &str) -> Result<Date, String> {
+    if RANGE_NAMES.contains(&value) || parse_offset(value).is_some() {
+        let day = Range::parse(value, Local::now().date_naive())?.start;
+        return Date::parse(&day.format("%Y-%m-%d").to_string()).map_err(|error| error.to_string());
+    }
+
+    Date::parse(value).map_err(|error| error.to_string())
+}
+
+/// Task IDs as shown by 'list', like `3` or `1,3,5-9`, as 0-based indexes.
+pub(crate) fn parse_ids(value: &str) -> Result<Vec<usize>, String> {
+    let invalid = || format!("'{}' is not a task ID or range like 1,3,5-9.", value);
+    let id = |part: &str| part.parse::<usize>().ok().filter(|id| *id > 0).ok_or_else(invalid);
+    let mut ids = Vec::new();
+
+    for part in value.split(',') {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (id(first)?, id(last)?);
+                if first > last {
+                    return Err(invalid());
+                }
+                ids.extend(first - 1..last);
+            }
+            None => ids.push(id(part)? - 1),
+        }
+    }
+
+    Ok(ids)
+}
+
+/// Days in an offset like `-3d`, `+2w` or `1w`.
+fn parse_offset(value: &str) -> Option<i64> {
+    let (number, days_per_unit) = match value.chars().last()? {
+        'd' => (&value[..value.len() - 1], 1),
+        'w' => (&value[..value.len() - 1], 7),
+        _ => return None,
+    };
+
+    number.strip_prefix('+').unwrap_or(number).parse::<i64>().ok().map(|number| number * days_per_unit)
+}
+
+/// A filter on one of a task's dates, e.g. `completed:last_week`, `created.after:2024-01-01`
+/// or `due.before:+1w`. Tasks without the date never match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Term {
+    field: Field,
+    comparison: Comparison,
+    range: Range,
+}
+
+impl Term {
+    pub(crate) fn parse(term: &str) -> Result<Term, String> {
+        let (key, value) = term.split_once(':').ok_or_else(|| format!("'{}' is not a filter.", term))?;
+
+        let (field, comparison) = match key.split_once('.') {
+            Some((field, "before")) => (field, Comparison::Before),
+            Some((field, "after")) => (field, Comparison::After),
+            Some((_, modifier)) => return Err(format!("Unknown modifier '{}' in '{}'. Expected before or after.", modifier, term)),
+            None => (key, Comparison::Within),
+        };
+
+        let field = match field {
+            "created" => Field::Created,
+            "modified" => Field::Modified,
+            "completed" => Field::Completed,
+            "due" => Field::Due,
+            "scheduled" => Field::Scheduled,
+            "wait" => Field::Wait,
+            "until" => Field::Until,
+            _ => return Err(format!("Unknown field '{}' in '{}'. Expected one of: {}.", field, term, FIELD_NAMES.join(", "))),
+        };
+
+        let range = Range::parse(value, Local::now().date_naive())?;
+        Ok(Term { field, comparison, range })
+    }
+
+    pub(crate) fn matches(&self, task: &Task) -> bool {
+        let day = match self.field {
+            Field::Created => Some(task.created_at.date_naive()),
+            Field::Modified => Some(task.modified_at.date_naive()),
+            Field::Completed => task.completed_at.map(|at| at.date_naive()),
+            Field::Due => task.due_date.as_ref().ok().and_then(|date| date.to_naive_date()),
+            Field::Scheduled => task.scheduled.as_ref().and_then(|date| date.to_naive_date()),
+            Field::Wait => task.wait.as_ref().and_then(|date| date.to_naive_date()),
+            Field::Until => task.until.as_ref().and_then(|date| date.to_naive_date()),
+        };
+
+        match day {
+            Some(day) => match self.comparison {
+                Comparison::Within => self.range.start <= day && day < self.range.end,
+                Comparison::Before => day < self.range.start,
+                Comparison::After => day >= self.range.end,
+            },
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    fn range(start: &str, end: &str) -> Range {
+        Range { start: day(start), end: day(end) }
+    }
+
+    #[test]
+    fn ids_and_ranges_become_indexes() {
+        assert_eq!(parse_ids("3"), Ok(vec![2]));
+        assert_eq!(parse_ids("1,3,5-7"), Ok(vec![0, 2, 4, 5, 6]));
+        assert_eq!(parse_ids("4-4"), Ok(vec![3]));
+        assert_eq!(parse_ids("300"), Ok(vec![299]));
+    }
+
+    #[test]
+    fn invalid_ids_are_rejected() {
+        for value in ["0", "", "1,", "-2", "5-3", "1-x", "tag:home", "due:today"] {
+            assert!(parse_ids(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn offsets_count_days_and_weeks() {
+        assert_eq!(parse_offset("3d"), Some(3));
+        assert_eq!(parse_offset("+2w"), Some(14));
+        assert_eq!(parse_offset("-1w"), Some(-7));
+        assert_eq!(parse_offset("today"), None);
+        assert_eq!(parse_offset("d"), None);
+    }
+
+    #[test]
+    fn named_ranges_are_relative_to_today() {
+        // A Wednesday.
+        let today = day("2024-01-10");
+        assert_eq!(Range::parse("today", today), Ok(range("2024-01-10", "2024-01-11")));
+        assert_eq!(Range::parse("yesterday", today), Ok(range("2024-01-09", "2024-01-10")));
+        assert_eq!(Range::parse("this_week", today), Ok(range("2024-01-08", "2024-01-15")));
+        assert_eq!(Range::parse("next_week", today), Ok(range("2024-01-15", "2024-01-22")));
+        assert_eq!(Range::parse("this_month", today), Ok(range("2024-01-01", "2024-02-01")));
+        assert_eq!(Range::parse("last_month", today), Ok(range("2023-12-01", "2024-01-01")));
+        assert_eq!(Range::parse("-3d", today), Ok(range("2024-01-07", "2024-01-08")));
+        assert_eq!(Range::parse("2024-03-05", today), Ok(range("2024-03-05", "2024-03-06")));
+        assert!(Range::parse("someday", today).is_err());
+    }
+
+    #[test]
+    fn terms_need_a_known_field_and_modifier() {
+        assert!(Term::parse("due.before:today").is_ok());
+        assert!(Term::parse("completed:last_week").is_ok());
+        assert!(Term::parse("due").is_err());
+        assert!(Term::parse("born:today").is_err());
+        assert!(Term::parse("due.around:today").is_err());
+        assert!(Term::parse("due:someday").is_err());
+    }
+
+    #[test]
+    fn terms_compare_against_the_range() {
+        let mut task = Task::from("Report".to_string(), None, Some(Date::parse("2024-01-10")), None, None, None, None, crate::tasks::Status::Todo, false);
+        let term = |comparison, start, end| Term { field: Field::Due, comparison, range: range(start, end) };
+
+        assert!(term(Comparison::Within, "2024-01-08", "2024-01-15").matches(&task));
+        assert!(!term(Comparison::Within, "2024-01-11", "2024-01-12").matches(&task));
+        assert!(term(Comparison::Before, "2024-01-11", "2024-01-12").matches(&task));
+        assert!(!term(Comparison::Before, "2024-01-10", "2024-01-11").matches(&task));
+        assert!(term(Comparison::After, "2024-01-09", "2024-01-10").matches(&task));
+        assert!(!term(Comparison::After, "2024-01-10", "2024-01-11").matches(&task));
+
+        // Tasks without the date never match.
+        task.due_date = Err(crate::dates::DateTimeError::UnspecifiedDate);
+        assert!(!term(Comparison::Before, "2100-01-01", "2100-01-02").matches(&task));
+    }
+}
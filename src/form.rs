@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+use chrono::Duration;
+use crate::{Date, Time, TaskList};
+use crate::attributes::Attributes;
+use crate::command_handler::CommandError;
+use crate::dates::{format_duration, parse_duration, DateTimeError};
+use crate::editor::edit_text;
+use crate::filter::parse_date;
+use crate::tasks::{Priority, Task};
+
+const HEADER: &str = "\
+# Edit the task, save and quit to apply. Lines starting with '#' are ignored.
+# Leave a value empty to clear it. Delete everything to cancel.
+";
+
+/// Everything after this line is the description.
+const DESCRIPTION: &str = "Description:";
+
+/// A task as the user edits it in $EDITOR, one 'Field: value' line per property.
+struct TaskForm {
+    title: String,
+    due_date: Option<Date>,
+    due_time: Option<Time>,
+    scheduled: Option<Date>,
+    wait: Option<Date>,
+    until: Option<Date>,
+    priority: Option<Priority>,
+    project: Option<String>,
+    context: Option<String>,
+    estimate: Option<Duration>,
+    remind: Option<Duration>,
+    tags: Vec<String>,
+    flagged: bool,
+    /// Custom attributes, keyed by name.
+    attributes: BTreeMap<String, String>,
+    description: Option<String>,
+}
+
+fn iso_date(date: &Option<Date>) -> String {
+    date.as_ref().map(|date| date.as_iso_date_string()).unwrap_or_default()
+}
+
+impl TaskForm {
+    fn from_task(task: &Task) -> TaskForm {
+        TaskForm {
+            title: task.title.clone(),
+            due_date: task.due_date.clone().ok(),
+            due_time: task.due_time.clone().ok(),
+            scheduled: task.scheduled.clone(),
+            wait: task.wait.clone(),
+            until: task.until.clone(),
+            priority: task.priority,
+            project: task.project.clone(),
+            context: task.context.clone(),
+            estimate: task.estimate(),
+            remind: task.remind(),
+            tags: task.tags.clone(),
+            flagged: task.flagged,
+            attributes: task.attributes.clone(),
+            description: task.description.clone(),
+        }
+    }
+
+    /// Lists every attribute declared in the config, so unset ones can be filled in.
+    fn to_text(&self, attributes: &Attributes) -> String {
+        let mut text = String::from(HEADER);
+        text.push_str(&format!("Title: {}\n", self.title));
+        text.push_str(&format!("Due Date: {}\n", iso_date(&self.due_date)));
+        text.push_str(&format!("Due Time: {}\n", self.due_time.as_ref().map(|time| time.as_24_hour_time_string()).unwrap_or_default()));
+        text.push_str(&format!("Scheduled: {}\n", iso_date(&self.scheduled)));
+        text.push_str(&format!("Wait: {}\n", iso_date(&self.wait)));
+        text.push_str(&format!("Until: {}\n", iso_date(&self.until)));
+        text.push_str(&format!("Priority: {}\n", self.priority.map(|priority| priority.as_str().to_lowercase()).unwrap_or_default()));
+        text.push_str(&format!("Project: {}\n", self.project.clone().unwrap_or_default()));
+        text.push_str(&format!("Context: {}\n", self.context.as_ref().map(|context| format!("@{}", context)).unwrap_or_default()));
+        text.push_str(&format!("Estimate: {}\n", self.estimate.map(|estimate| format_duration(estimate).replace(' ', "")).unwrap_or_default()));
+        text.push_str(&format!("Remind: {}\n", self.remind.map(|remind| format_duration(remind).replace(' ', "")).unwrap_or_default()));
+        text.push_str(&format!("Tags: {}\n", self.tags.join(", ")));
+        text.push_str(&format!("Flagged: {}\n", if self.flagged { "yes" } else { "no" }));
+        for name in attributes.keys() {
+            text.push_str(&format!("{}: {}\n", name, self.attributes.get(name).cloned().unwrap_or_default()));
+        }
+        text.push_str(&format!("\n{}\n{}\n", DESCRIPTION, self.description.clone().unwrap_or_default()));
+        text.lines().map(|line| format!("{}\n", line.trim_end())).collect()
+    }
+
+    /// Reads the form back, validating values with the same rules as the command line.
+    fn parse(text: &str, attributes: &Attributes) -> Result<TaskForm, String> {
+        let mut form = TaskForm {
+            title: String::new(),
+            due_date: None,
+            due_time: None,
+            scheduled: None,
+            wait: None,
+            until: None,
+            priority: None,
+            project: None,
+            context: None,
+            estimate: None,
+            remind: None,
+            tags: Vec::new(),
+            flagged: false,
+            attributes: BTreeMap::new(),
+            description: None,
+        };
+        let mut description: Option<Vec<&str>> = None;
+
+        for line in text.lines() {
+            if let Some(lines) = description.as_mut() {
+                lines.push(line);
+                continue;
+            }
+            if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+            if line.trim() == DESCRIPTION {
+                description = Some(Vec::new());
+                continue;
+            }
+
+            let (field, value) = line.split_once(':').ok_or_else(|| format!("Expected 'Field: value', found '{}'.", line))?;
+            let value = value.trim();
+            let text = || Some(value.to_string()).filter(|value| !value.is_empty());
+
+            match field.trim().to_lowercase().as_str() {
+                "title" => form.title = value.to_string(),
+                "due date" => form.due_date = text().map(|date| parse_date(&date)).transpose().map_err(|error| format!("Due Date: {}", error))?,
+                "due time" => form.due_time = text().map(|time| Time::parse(&time)).transpose().map_err(|error| format!("Due Time: {}", error))?,
+                "scheduled" => form.scheduled = text().map(|date| parse_date(&date)).transpose().map_err(|error| format!("Scheduled: {}", error))?,
+                "wait" => form.wait = text().map(|date| parse_date(&date)).transpose().map_err(|error| format!("Wait: {}", error))?,
+                "until" => form.until = text().map(|date| parse_date(&date)).transpose().map_err(|error| format!("Until: {}", error))?,
+                "priority" => form.priority = text().filter(|priority| priority != "none").map(|priority| Priority::parse(&priority)).transpose()?,
+                "project" => form.project = text(),
+                "context" => form.context = text().map(|context| context.trim_start_matches('@').to_string()).filter(|context| !context.is_empty()),
+                "estimate" => form.estimate = text().map(|estimate| parse_duration(&estimate)).transpose().map_err(|error| format!("Estimate: {}", error))?,
+                "remind" => form.remind = text().map(|remind| parse_duration(&remind)).transpose().map_err(|error| format!("Remind: {}", error))?,
+                "tags" => form.tags = value.split(',').map(|tag| tag.trim().to_string()).filter(|tag| !tag.is_empty()).collect(),
+                "flagged" => form.flagged = match value.to_lowercase().as_str() {
+                    "yes" | "true" => true,
+                    "no" | "false" | "" => false,
+                    _ => return Err(format!("Flagged: expected yes or no, found '{}'.", value)),
+                },
+                name => match attributes.get(name) {
+                    Some(attribute) => {
+                        if let Some(value) = text() {
+                            form.attributes.insert(name.to_string(), attribute.parse(name, &value)?);
+                        }
+                    }
+                    None => return Err(format!("Unknown field '{}'.", name)),
+                },
+            }
+        }
+
+        if form.title.is_empty() {
+            return Err("Title can't be empty.".to_string());
+        }
+
+        form.description = description
+            .map(|lines| lines.join("\n").trim().to_string())
+            .filter(|description| !description.is_empty());
+
+        Ok(form)
+    }
+}
+
+/// Opens the task at `index` in $EDITOR and applies the changes through `TaskList::edit_task`.
+/// Invalid input re-opens the editor with the error at the top.
+pub(crate) fn edit_in_editor(task_list: &mut TaskList, index: usize, attributes: &Attributes) -> Result<String, CommandError> {
+    let task = task_list.tasks.get(index).ok_or_else(|| CommandError::TaskNotFound((index + 1).to_string()))?;
+    let original = TaskForm::from_task(task);
+    let mut text = original.to_text(attributes);
+
+    let form = loop {
+        let edited = edit_text(&text, "txt").map_err(CommandError::EditorError)?;
+        if edited.lines().all(|line| line.trim().is_empty()) {
+            return Ok("Edit cancelled.".to_string());
+        }
+
+        match TaskForm::parse(&edited, attributes) {
+            Ok(form) => break form,
+            Err(error) => {
+                let kept: Vec<&str> = edited.lines().filter(|line| !line.starts_with("# Error:")).collect();
+                text = format!("# Error: {}\n{}\n", error, kept.join("\n"));
+            }
+        }
+    };
+
+    let changed = |a: String, b: String| if a != b { Some(b) } else { None };
+    let time_string = |time: &Option<Time>| time.as_ref().map(|time| time.as_24_hour_time_string()).unwrap_or_default();
+
+    let title = changed(original.title.clone(), form.title.clone());
+    let description = changed(original.description.clone().unwrap_or_default(), form.description.clone().unwrap_or_default());
+    let due_date = changed(iso_date(&original.due_date), iso_date(&form.due_date))
+        .map(|_| form.due_date.clone().ok_or(DateTimeError::UnspecifiedDate));
+    let due_time = changed(time_string(&original.due_time), time_string(&form.due_time))
+        .map(|_| form.due_time.clone().ok_or(DateTimeError::UnspecifiedTime));
+    let flag = if original.flagged != form.flagged { Some(form.flagged) } else { None };
+    let priority = if original.priority != form.priority { Some(form.priority) } else { None };
+    let project = if original.project != form.project { Some(form.project.clone()) } else { None };
+    let context = if original.context != form.context { Some(form.context.clone()) } else { None };
+    let remind = if original.remind != form.remind { Some(form.remind) } else { None };
+    let estimate = if original.estimate != form.estimate { Some(form.estimate) } else { None };
+    let date_changed = |a: &Option<Date>, b: &Option<Date>| if iso_date(a) != iso_date(b) { Some(b.clone()) } else { None };
+    let scheduled = date_changed(&original.scheduled, &form.scheduled);
+    let wait = date_changed(&original.wait, &form.wait);
+    let until = date_changed(&original.until, &form.until);
+    // Values of attributes no longer declared in the config aren't shown, so they're kept.
+    let changed_attributes: Vec<(String, Option<String>)> = attributes.keys()
+        .filter(|name| original.attributes.get(*name) != form.attributes.get(*name))
+        .map(|name| (name.clone(), form.attributes.get(name).cloned()))
+        .collect();
+
+    let unchanged = title.is_none() && description.is_none() && due_date.is_none() && due_time.is_none()
+        && flag.is_none() && priority.is_none() && project.is_none() && estimate.is_none()
+        && scheduled.is_none() && wait.is_none() && until.is_none() && original.tags == form.tags
+        && context.is_none() && remind.is_none() && changed_attributes.is_empty();
+    if unchanged {
+        return Ok("No changes.".to_string());
+    }
+
+    let missing_from = |tags: &[String], tag: &String| !tags.iter().any(|other| other.eq_ignore_ascii_case(tag));
+    let added = form.tags.iter().filter(|tag| missing_from(&original.tags, tag)).cloned().collect();
+    let removed = original.tags.iter().filter(|tag| missing_from(&form.tags, tag)).cloned().collect();
+
+    task_list.edit_task(index, title, description, due_date, due_time, flag, priority, project, estimate)
+        .and_then(|_| task_list.retag_task(index, added, removed))
+        .and_then(|_| match scheduled.is_some() || wait.is_some() || until.is_some() {
+            true => task_list.schedule_task(index, scheduled, wait, until),
+            false => Ok(String::new()),
+        })
+        .and_then(|_| match remind {
+            Some(remind) => task_list.set_task_reminder(index, remind),
+            None => Ok(String::new()),
+        })
+        .and_then(|_| match context {
+            Some(context) => task_list.set_task_context(index, context),
+            None => Ok(String::new()),
+        })
+        .and_then(|_| match changed_attributes.is_empty() {
+            true => Ok(String::new()),
+            false => task_list.set_attributes(index, changed_attributes),
+        })
+        .map(|_| "Task successfully edited.".to_string())
+        .map_err(|_| CommandError::TaskNotFound((index + 1).to_string()))
+}
@@ -0,0 +1,152 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use crate::Config;
+use crate::tasks::{Status, Task};
+
+/// Points in a task's life where hook scripts run. Scripts are picked by their file name prefix,
+/// e.g. `on-add-tag-bugs` runs on `Add`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Event {
+    Launch,
+    Add,
+    Modify,
+    Complete,
+    Delete,
+}
+
+impl Event {
+    fn prefix(&self) -> &'static str {
+        match self {
+            Event::Launch => "on-launch",
+            Event::Add => "on-add",
+            Event::Modify => "on-modify",
+            Event::Complete => "on-complete",
+            Event::Delete => "on-delete",
+        }
+    }
+}
+
+/// Executables in the hooks directory for `event`, in name order.
+fn scripts(config: &Config, event: Event) -> Vec<PathBuf> {
+    let mut scripts: Vec<PathBuf> = match fs::read_dir(&config.hooks_dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with(event.prefix())))
+            .filter(|path| is_executable(path))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    scripts.sort();
+    scripts
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+fn name(script: &Path) -> String {
+    script.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+/// Runs a script with `input` on stdin and returns its stdout lines. A non-zero exit rejects the
+/// change, with the script's output as the reason.
+fn run(script: &Path, input: &str) -> Result<Vec<String>, String> {
+    let name = name(script);
+    let mut child = Command::new(script)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("Could not run hook '{}': {}", name, error))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that doesn't read its input closes the pipe early, which is fine.
+        let _ = stdin.write_all(input.as_bytes());
+    }
+
+    let output = child.wait_with_output().map_err(|error| format!("Hook '{}' failed: {}", name, error))?;
+    let stdout: Vec<String> = String::from_utf8_lossy(&output.stdout).lines().map(|line| line.to_string()).collect();
+
+    match output.status.success() {
+        true => Ok(stdout),
+        false => {
+            let mut reason = stdout.join(" ");
+            if reason.trim().is_empty() {
+                reason = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            }
+            match reason.is_empty() {
+                true => Err(format!("Rejected by hook '{}'.", name)),
+                false => Err(format!("Rejected by hook '{}': {}", name, reason)),
+            }
+        }
+    }
+}
+
+/// Runs the scripts of `event` in turn on `task`. Each gets the task as a JSON line on stdin, after
+/// `original` for modifications, and may print a changed task as its first line. Other lines are feedback.
+fn run_on_task(config: &Config, event: Event, original: Option<&Task>, mut task: Task) -> Result<(Task, Vec<String>), String> {
+    let mut feedback = Vec::new();
+
+    for script in scripts(config, event) {
+        let mut input = String::new();
+        if let Some(original) = original {
+            input.push_str(&serde_json::to_string(original).map_err(|error| error.to_string())?);
+            input.push('\n');
+        }
+        input.push_str(&serde_json::to_string(&task).map_err(|error| error.to_string())?);
+        input.push('\n');
+
+        let mut lines = run(&script, &input)?.into_iter();
+        match lines.next() {
+            Some(first) if first.trim_start().starts_with('{') => {
+                let num = task.num;
+                task = serde_json::from_str(&first).map_err(|error| format!("Hook '{}' printed an invalid task: {}", name(&script), error))?;
+                task.num = num;
+            }
+            Some(first) => feedback.push(first),
+            None => {}
+        }
+        feedback.extend(lines.filter(|line| !line.trim().is_empty()));
+    }
+
+    Ok((task, feedback))
+}
+
+/// Runs the on-launch scripts before a command. Any of them can stop it by exiting non-zero.
+pub(crate) fn on_launch(config: &Config) -> Result<Vec<String>, String> {
+    let mut feedback = Vec::new();
+    for script in scripts(config, Event::Launch) {
+        feedback.extend(run(&script, "")?.into_iter().filter(|line| !line.trim().is_empty()));
+    }
+    Ok(feedback)
+}
+
+/// Runs the on-add scripts on a new task, which they may change or reject.
+pub(crate) fn on_add(config: &Config, task: Task) -> Result<(Task, Vec<String>), String> {
+    run_on_task(config, Event::Add, None, task)
+}
+
+/// Runs the on-modify scripts on a changed task, then the on-complete scripts if it was just completed.
+pub(crate) fn on_modify(config: &Config, original: &Task, task: Task) -> Result<(Task, Vec<String>), String> {
+    let (task, mut feedback) = run_on_task(config, Event::Modify, Some(original), task)?;
+    if task.status == Status::Done && original.status != Status::Done {
+        let (task, more) = run_on_task(config, Event::Complete, Some(original), task)?;
+        feedback.extend(more);
+        return Ok((task, feedback));
+    }
+    Ok((task, feedback))
+}
+
+/// Runs the on-delete scripts on a task about to be deleted, which they may reject.
+pub(crate) fn on_delete(config: &Config, task: &Task) -> Result<Vec<String>, String> {
+    run_on_task(config, Event::Delete, None, task.clone()).map(|(_, feedback)| feedback)
+}
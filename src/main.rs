@@ -1,47 +1,112 @@
 use std::env;
+use std::path::Path;
 
 mod tasks;
 mod dates;
 mod command_handler;
 mod utils;
 mod config;
+mod theme;
+mod tui;
+mod shell;
+mod completions;
+mod cli;
+mod filter;
+mod report;
+mod editor;
+mod form;
+mod attributes;
+mod daemon;
+mod hooks;
+mod paths;
+mod search;
 
 use tasks::{TaskList};
 use dates::{Date, Time};
 use command_handler::{command_handler};
 use crate::command_handler::CommandError;
+use crate::cli::take_global_option;
 use crate::config::Config;
 use crate::utils::{save_tasks, SaveError};
 
-fn display_command_response(response: Result<String, CommandError>, config: Config) {
+pub(crate) fn display_command_response(response: Result<String, CommandError>, config: Config) {
+    let theme = config.theme();
     match response {
-        Ok(message) => println!("{}", message.as_str()),
-        Err(error) => println!("{}{}\x1b[0m{}", config.error_color, error, &config.default_color),
+        Ok(message) if message.is_empty() => {}
+        Ok(message) => println!("{}", theme.default.paint(message.as_str())),
+        Err(error) => println!("{}", theme.error.paint(&error.to_string())),
     }
 }
 
-fn display_save_response(response: Result<String, SaveError>, config: Config) {
+pub(crate) fn display_save_response(response: Result<String, SaveError>, config: Config) {
+    let theme = config.theme();
     match response {
-        Ok(message) => println!("{}{}", config.success_color, message.as_str()),
-        Err(error) => println!("{}{}\x1b[0m{}", config.error_color, error, &config.default_color),
+        Ok(message) => println!("{}", theme.success.paint(message.as_str())),
+        Err(error) => println!("{}", theme.error.paint(&error.to_string())),
     }
 }
 
+/// Options given before the command, see `cli::GLOBAL_OPTIONS`.
+struct GlobalOptions {
+    config: Option<String>,
+    data_file: Option<String>,
+    list: Option<String>,
+}
+
+/// Takes the global options off the front of the command line. Errors name the first bad option.
+fn global_options(command: &mut Vec<String>) -> Result<GlobalOptions, String> {
+    Ok(GlobalOptions {
+        config: take_global_option(command, "--config")?,
+        data_file: take_global_option(command, "--data-file")?,
+        list: take_global_option(command, "--list")?,
+    })
+}
+
 fn main() {
-    let config: Config;
-    let read_conf = Config::read_from_file("data/config.json".to_string());
+    let args: Vec<String> = env::args().collect();
+    let mut command = args[1..].to_owned();
+    let options = match global_options(&mut command) {
+        Ok(options) => options,
+        Err(e) => {
+            println!("{}", Config::default().theme().error.paint(&e));
+            return;
+        }
+    };
+
+    let mut config: Config;
+    let config_file = paths::config_file(options.config);
+    let read_conf = Config::read_from_file(config_file.path.clone());
     match read_conf {
         Ok(conf) => {
             config = conf;
+            config.paths.config = config_file;
         },
         Err(e) => {
             config = Config::default();
-            let _ = config.save_to_file();
+            let exists = Path::new(&config_file.path).exists();
+            config.paths.config = config_file;
+            // Never overwrite a config that merely failed to parse.
+            if !exists {
+                let _ = config.save_to_file();
+            }
             display_save_response(Err(e), config.clone());
         }
     }
+    config.paths.data = paths::data_file(options.data_file);
+
+    if let Err(e) = config.check() {
+        println!("{}", config.theme().error.paint(&e));
+    }
+
+    if let Some(name) = options.list {
+        match config.with_list(&name) {
+            Ok(with_list) => config = with_list,
+            Err(e) => {
+                println!("{}", config.theme().error.paint(&e));
+                return;
+            }
+        }
+    }
 
-    let args: Vec<String> = env::args().collect();
-    let command = args[1..].to_owned();
     display_command_response(command_handler(command, config.clone()), config.clone());
 }
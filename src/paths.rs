@@ -0,0 +1,89 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Where setups from before XDG support keep their config, relative to the working directory.
+const LEGACY_CONFIG: &str = "data/config.json";
+
+/// A path and what it was taken from, as 'doctor' reports it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Resolved {
+    pub(crate) path: String,
+    pub(crate) source: String,
+}
+
+impl Resolved {
+    fn new(path: impl AsRef<Path>, source: &str) -> Resolved {
+        Resolved { path: path.as_ref().to_string_lossy().to_string(), source: source.to_string() }
+    }
+}
+
+/// Paths picked at startup from flags and the environment rather than the config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Paths {
+    pub(crate) config: Resolved,
+    /// Data file given with '--data-file' or `TASKNINJA_DATA`, used instead of the current list's.
+    pub(crate) data: Option<Resolved>,
+}
+
+impl Default for Paths {
+    fn default() -> Paths {
+        Paths { config: config_file(None), data: None }
+    }
+}
+
+/// `$name` when it's set to an absolute path. Relative values are ignored, as the XDG spec asks.
+fn absolute_var(name: &str) -> Option<PathBuf> {
+    env::var_os(name).map(PathBuf::from).filter(|path| path.is_absolute())
+}
+
+fn non_empty_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// `$XDG_<name>_HOME/taskninja`, else `~/<fallback>/taskninja`, else `data` in the working directory.
+fn base_dir(name: &str, fallback: &str) -> (PathBuf, String) {
+    let variable = format!("XDG_{}_HOME", name);
+    if let Some(dir) = absolute_var(&variable) {
+        return (dir.join("taskninja"), format!("${}", variable));
+    }
+    match absolute_var("HOME") {
+        Some(home) => (home.join(fallback).join("taskninja"), format!("~/{}", fallback)),
+        None => (PathBuf::from("data"), "working directory".to_string()),
+    }
+}
+
+/// Directory of the config file and hooks.
+pub(crate) fn config_dir() -> PathBuf {
+    base_dir("CONFIG", ".config").0
+}
+
+/// Directory of the task lists and the archive.
+pub(crate) fn data_dir() -> PathBuf {
+    base_dir("DATA", ".local/share").0
+}
+
+/// The config file: '--config', then `TASKNINJA_CONFIG`, then `config.json` in the XDG config directory.
+/// Falls back to `data/config.json` in the working directory when only that exists.
+pub(crate) fn config_file(flag: Option<String>) -> Resolved {
+    if let Some(path) = flag {
+        return Resolved::new(path, "--config");
+    }
+    if let Some(path) = non_empty_var("TASKNINJA_CONFIG") {
+        return Resolved::new(path, "$TASKNINJA_CONFIG");
+    }
+
+    let (dir, source) = base_dir("CONFIG", ".config");
+    let path = dir.join("config.json");
+    if !path.exists() && Path::new(LEGACY_CONFIG).exists() {
+        return Resolved::new(LEGACY_CONFIG, "working directory, legacy");
+    }
+    Resolved::new(path, &source)
+}
+
+/// The data file set with '--data-file' or `TASKNINJA_DATA`, if any.
+pub(crate) fn data_file(flag: Option<String>) -> Option<Resolved> {
+    match flag {
+        Some(path) => Some(Resolved::new(path, "--data-file")),
+        None => non_empty_var("TASKNINJA_DATA").map(|path| Resolved::new(path, "$TASKNINJA_DATA")),
+    }
+}
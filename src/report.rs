@@ -0,0 +1,319 @@
+use std::collections::BTreeMap;
+use chrono::{Datelike, Duration};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::{Config, TaskList};
+use crate::attributes::Attributes;
+use crate::cli::capitalize;
+use crate::dates::format_duration;
+use crate::tasks::{format_date, format_timestamp, Task, SORT_KEYS};
+
+/// Reports the 'report' command can print.
+pub(crate) const REPORT_NAMES: [&str; 2] = ["effort", "waiting"];
+
+/// Width of the name column in report tables.
+const NAME_COLUMN: usize = 32;
+
+/// Estimated and tracked time of a group of tasks.
+struct Effort {
+    estimated: Duration,
+    tracked: Duration,
+    /// Estimates and tracked time of the tasks that have both, for the actual/estimate ratio.
+    compared_estimate: Duration,
+    compared_tracked: Duration,
+}
+
+impl Effort {
+    fn new() -> Effort {
+        Effort {
+            estimated: Duration::zero(),
+            tracked: Duration::zero(),
+            compared_estimate: Duration::zero(),
+            compared_tracked: Duration::zero(),
+        }
+    }
+
+    fn add(&mut self, task: &Task) {
+        let tracked = task.tracked();
+        self.tracked = self.tracked + tracked;
+
+        if let Some(estimate) = task.estimate() {
+            self.estimated = self.estimated + estimate;
+            if tracked > Duration::zero() {
+                self.compared_estimate = self.compared_estimate + estimate;
+                self.compared_tracked = self.compared_tracked + tracked;
+            }
+        }
+    }
+
+    fn row(&self, name: &str) -> String {
+        let show = |duration: Duration| if duration > Duration::zero() { format_duration(duration) } else { "-".to_string() };
+        let ratio = match self.compared_estimate.num_minutes() {
+            0 => "-".to_string(),
+            minutes => format!("{}%", self.compared_tracked.num_minutes() * 100 / minutes),
+        };
+
+        format!("    {:<width$}{:>10}{:>10}{:>8}\n", fit(name, NAME_COLUMN - 2), show(self.estimated), show(self.tracked), ratio, width = NAME_COLUMN)
+    }
+}
+
+fn fit(text: &str, width: usize) -> String {
+    match text.chars().count() > width {
+        true => format!("{}…", text.chars().take(width - 1).collect::<String>()),
+        false => text.to_string(),
+    }
+}
+
+fn table(title: &str, rows: Vec<(String, Effort)>) -> String {
+    let mut table = format!("{}\n    {:<width$}{:>10}{:>10}{:>8}\n", title, "", "Estimate", "Tracked", "Actual", width = NAME_COLUMN);
+    for (name, effort) in rows {
+        table.push_str(&effort.row(&name));
+    }
+    table
+}
+
+/// Compares estimates with tracked time per task, project and week of completion. 'Actual' is
+/// tracked time as a share of the estimate, counting only tasks that have both.
+pub(crate) fn effort(task_list: &TaskList) -> String {
+    let tasks: Vec<&Task> = task_list.tasks.iter()
+        .filter(|task| task.estimate().is_some() || !task.time_entries.is_empty())
+        .collect();
+
+    if tasks.is_empty() {
+        return "No estimates or tracked time yet. Use 'add --estimate' and 'track' to record them.".to_string();
+    }
+
+    let mut by_task = Vec::new();
+    let mut by_project: BTreeMap<String, Effort> = BTreeMap::new();
+    let mut by_week: BTreeMap<String, Effort> = BTreeMap::new();
+
+    for task in tasks.iter() {
+        let mut effort = Effort::new();
+        effort.add(task);
+        by_task.push((format!("{}: {}", task.num, task.title), effort));
+
+        let project = task.project.clone().unwrap_or_else(|| "(no project)".to_string());
+        by_project.entry(project).or_insert_with(Effort::new).add(task);
+
+        if let Some(completed_at) = task.completed_at {
+            let day = completed_at.date_naive();
+            let monday = day - Duration::days(day.weekday().num_days_from_monday() as i64);
+            by_week.entry(format!("Week of {}", monday.format("%Y-%m-%d"))).or_insert_with(Effort::new).add(task);
+        }
+    }
+
+    let mut report = table("Effort by task:", by_task);
+    report.push('\n');
+    report.push_str(&table("Effort by project:", by_project.into_iter().collect()));
+    if !by_week.is_empty() {
+        report.push('\n');
+        report.push_str(&table("Effort by week completed:", by_week.into_iter().collect()));
+    }
+
+    report.push('\n');
+    report.push_str(&accuracy(&tasks));
+    report.push('\n');
+    report.push_str(&remaining_this_week(task_list));
+
+    report
+}
+
+/// How close completed tasks came to their estimates, on average.
+fn accuracy(tasks: &[&Task]) -> String {
+    let scores: Vec<f64> = tasks.iter()
+        .filter(|task| task.completed_at.is_some())
+        .filter_map(|task| match (task.estimate(), task.tracked()) {
+            (Some(estimate), tracked) if tracked > Duration::zero() && estimate > Duration::zero() => {
+                let error = (tracked - estimate).num_minutes().abs() as f64 / estimate.num_minutes() as f64;
+                Some((1.0 - error).max(0.0))
+            }
+            _ => None,
+        })
+        .collect();
+
+    match scores.len() {
+        0 => "Estimation accuracy: No completed tasks with both an estimate and tracked time.".to_string(),
+        count => format!("Estimation accuracy: {:.0}% across {} completed task{}.", scores.iter().sum::<f64>() / count as f64 * 100.0, count, if count == 1 { "" } else { "s" }),
+    }
+}
+
+/// Estimated work left on open tasks due this week, less the time already tracked on them.
+fn remaining_this_week(task_list: &TaskList) -> String {
+    let due = task_list.filter_tasks(vec!["incomplete", "due:this_week"]);
+    let unestimated = due.iter().filter(|task| task.estimate().is_none()).count();
+
+    let remaining = due.iter()
+        .filter_map(|task| task.estimate().map(|estimate| estimate - task.tracked()))
+        .filter(|remaining| *remaining > Duration::zero())
+        .fold(Duration::zero(), |total, remaining| total + remaining);
+
+    let mut line = format!("Remaining estimated work due this week: {} across {} open task{}.", format_duration(remaining), due.len(), if due.len() == 1 { "" } else { "s" });
+    if unestimated > 0 {
+        line.push_str(&format!(" {} of them {} no estimate.", unestimated, if unestimated == 1 { "has" } else { "have" }));
+    }
+    line
+}
+
+/// Open tasks hidden from 'list' by a wait date, soonest first.
+pub(crate) fn waiting(task_list: &TaskList, config: Config) -> String {
+    let mut tasks = task_list.filter_tasks(vec!["incomplete", "hidden"]);
+    if tasks.is_empty() {
+        return "No waiting tasks.".to_string();
+    }
+
+    tasks.sort_by_key(|task| task.wait.as_ref().map(|date| date.sort_key()));
+    tasks.iter()
+        .map(|task| {
+            let wait = task.wait.as_ref().map(|date| format_date(date, &config)).unwrap_or_default();
+            format!("{:>4}: {:<width$}hidden until {}\n", task.num, fit(&task.title, NAME_COLUMN), wait, width = NAME_COLUMN + 2)
+        })
+        .collect()
+}
+
+/// Columns a saved report can show, besides custom attributes.
+pub(crate) const COLUMN_NAMES: [&str; 15] = ["id", "title", "status", "priority", "due", "scheduled", "project", "tags", "context", "urgency", "estimate", "tracked", "created", "modified", "completed"];
+
+fn default_columns() -> Vec<String> {
+    ["id", "title", "status", "due"].iter().map(|column| column.to_string()).collect()
+}
+
+/// How a saved report is printed.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+    /// Aligned columns with a header.
+    #[default]
+    Table,
+    /// Every field of each task, as 'list' shows them.
+    Plain,
+    Csv,
+    Json,
+}
+
+/// A report saved under `reports` in the config, e.g.
+/// `"overdue": { "filter": "incomplete due.before:today", "sort": "due", "columns": ["id", "title", "due"] }`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavedReport {
+    /// Filter terms, as 'list' takes them.
+    #[serde(default)]
+    pub filter: String,
+    /// Sort key, as 'list --sort' takes it.
+    #[serde(default)]
+    pub sort: Option<String>,
+    #[serde(default = "default_columns")]
+    pub columns: Vec<String>,
+    #[serde(default)]
+    pub format: ReportFormat,
+}
+
+/// Reports the first saved report that clashes with a built-in one or uses an unknown column or sort key.
+pub(crate) fn check_reports(reports: &BTreeMap<String, SavedReport>, attributes: &Attributes) -> Result<(), String> {
+    for (name, report) in reports {
+        if REPORT_NAMES.contains(&name.as_str()) {
+            return Err(format!("Report '{}': the name is already used by a built-in report.", name));
+        }
+        if let Some(column) = report.columns.iter().find(|column| !COLUMN_NAMES.contains(&column.as_str()) && !attributes.contains_key(*column)) {
+            return Err(format!("Report '{}': unknown column '{}'. Expected one of: {}, or a custom attribute.", name, column, COLUMN_NAMES.join(", ")));
+        }
+        if let Some(sort) = report.sort.as_ref().filter(|sort| !SORT_KEYS.contains(&sort.as_str()) && !attributes.contains_key(*sort)) {
+            return Err(format!("Report '{}': unknown sort key '{}'. Expected one of: {}, or a custom attribute.", name, sort, SORT_KEYS.join(", ")));
+        }
+    }
+
+    Ok(())
+}
+
+/// A task's value in `column`. Numbers and tags keep their JSON type for the json format.
+fn cell(task: &Task, column: &str, config: &Config) -> Value {
+    let text = |text: Option<String>| text.map(Value::String).unwrap_or(Value::Null);
+    let duration = |duration: Duration| (duration > Duration::zero()).then(|| format_duration(duration));
+
+    match column {
+        "id" => Value::from(task.num),
+        "title" => Value::String(task.title.clone()),
+        "status" => Value::String(task.status.label().to_string()),
+        "priority" => text(task.priority.map(|priority| priority.as_str().to_string())),
+        "due" => text(task.due_date.as_ref().ok().map(|date| match &task.due_time {
+            Ok(time) if config.time_24_hour => format!("{} {}", format_date(date, config), time.as_24_hour_time_string()),
+            Ok(time) => format!("{} {}", format_date(date, config), time.as_12_hour_time_string()),
+            Err(_) => format_date(date, config),
+        })),
+        "scheduled" => text(task.scheduled.as_ref().map(|date| format_date(date, config))),
+        "project" => text(task.project.clone()),
+        "tags" => Value::from(task.tags.clone()),
+        "context" => text(task.context.as_ref().map(|context| format!("@{}", context))),
+        "urgency" => serde_json::Number::from_f64((task.urgency(&config.urgency) * 10.0).round() / 10.0).map(Value::Number).unwrap_or(Value::Null),
+        "estimate" => text(task.estimate().and_then(duration)),
+        "tracked" => text(duration(task.tracked())),
+        "created" => Value::String(format_timestamp(&task.created_at, config)),
+        "modified" => Value::String(format_timestamp(&task.modified_at, config)),
+        "completed" => text(task.completed_at.as_ref().map(|at| format_timestamp(at, config))),
+        attribute => text(task.attributes.get(attribute).cloned()),
+    }
+}
+
+fn cell_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        Value::Array(items) => items.iter().map(cell_text).collect::<Vec<String>>().join(" "),
+        value => value.to_string(),
+    }
+}
+
+/// Prints `tasks`, already filtered and sorted, the way `report` asks for.
+pub(crate) fn saved(task_list: &TaskList, tasks: Vec<Task>, report: &SavedReport, config: Config) -> String {
+    let rows: Vec<Vec<Value>> = tasks.iter()
+        .map(|task| report.columns.iter().map(|column| cell(task, column, &config)).collect())
+        .collect();
+
+    match report.format {
+        ReportFormat::Plain => task_list.tasks_to_string(tasks, config),
+        // Written by hand to keep the fields in column order. One task per line.
+        ReportFormat::Json => {
+            let objects: Vec<String> = rows.iter()
+                .map(|row| {
+                    let fields: Vec<String> = report.columns.iter().zip(row)
+                        .map(|(column, value)| format!("{}: {}", Value::String(column.clone()), value))
+                        .collect();
+                    format!("  {{{}}}", fields.join(", "))
+                })
+                .collect();
+            match objects.is_empty() {
+                true => "[]".to_string(),
+                false => format!("[\n{}\n]", objects.join(",\n")),
+            }
+        }
+        ReportFormat::Csv => {
+            let quote = |field: String| match field.contains([',', '"', '\n']) {
+                true => format!("\"{}\"", field.replace('"', "\"\"")),
+                false => field,
+            };
+            std::iter::once(report.columns.join(","))
+                .chain(rows.iter().map(|row| row.iter().map(|value| quote(cell_text(value))).collect::<Vec<String>>().join(",")))
+                .collect::<Vec<String>>()
+                .join("\n")
+        }
+        ReportFormat::Table => {
+            if rows.is_empty() {
+                return "No matching tasks.".to_string();
+            }
+
+            let rows: Vec<Vec<String>> = rows.iter().map(|row| row.iter().map(|value| fit(&cell_text(value), NAME_COLUMN)).collect()).collect();
+            let widths: Vec<usize> = report.columns.iter().enumerate()
+                .map(|(i, column)| rows.iter().map(|row| row[i].chars().count()).chain(std::iter::once(column.len())).max().unwrap_or(0))
+                .collect();
+            let line = |cells: Vec<String>| cells.iter().zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect::<Vec<String>>()
+                .join("  ")
+                .trim_end()
+                .to_string();
+
+            std::iter::once(line(report.columns.iter().map(|column| capitalize(column)).collect()))
+                .chain(rows.into_iter().map(line))
+                .collect::<Vec<String>>()
+                .join("\n")
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use std::ops::Range;
+use regex::{Regex, RegexBuilder};
+use crate::tasks::Task;
+use crate::theme::Theme;
+
+/// Fields 'search' looks in, and how much a match in each counts towards a task's rank.
+pub(crate) const SEARCH_FIELDS: [&str; 5] = ["title", "description", "project", "tags", "annotations"];
+
+fn weight(field: &str) -> i64 {
+    match field {
+        "title" => 3,
+        "project" | "tags" => 2,
+        _ => 1,
+    }
+}
+
+/// How the query is matched against a field.
+pub(crate) enum Matcher {
+    /// A substring or regular expression. Substrings are escaped into one.
+    Pattern(Regex),
+    /// The query's characters in order, with anything in between, e.g. 'grcs' finds 'groceries'.
+    Fuzzy { query: Vec<char>, case_sensitive: bool },
+}
+
+impl Matcher {
+    /// Matches `query` as a substring, or as a regular expression or fuzzily. Case is ignored unless `exact`.
+    pub(crate) fn new(query: &str, regex: bool, fuzzy: bool, exact: bool) -> Result<Matcher, String> {
+        if fuzzy {
+            return Ok(Matcher::Fuzzy { query: query.chars().filter(|c| !c.is_whitespace()).collect(), case_sensitive: exact });
+        }
+
+        let pattern = if regex { query.to_string() } else { regex::escape(query) };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!exact)
+            .build()
+            .map(Matcher::Pattern)
+            .map_err(|error| {
+                // Syntax errors come with the pattern drawn over several lines. Keep just the reason.
+                let error = error.to_string();
+                let reason = error.lines().find_map(|line| line.strip_prefix("error: ")).unwrap_or(&error).to_string();
+                format!("Invalid regular expression: {}.", reason)
+            })
+    }
+
+    /// How well `text` matches, higher is better, and the byte ranges that matched.
+    fn find(&self, text: &str) -> Option<(i64, Vec<Range<usize>>)> {
+        match self {
+            Matcher::Pattern(regex) => {
+                let ranges: Vec<Range<usize>> = regex.find_iter(text).map(|found| found.range()).filter(|range| !range.is_empty()).collect();
+                let first = ranges.first()?.start;
+                // Earlier and more frequent matches rank higher.
+                Some((100 - first.min(50) as i64 + 10 * ranges.len().min(5) as i64, ranges))
+            }
+            Matcher::Fuzzy { query, case_sensitive } => fuzzy(query, text, *case_sensitive),
+        }
+    }
+}
+
+/// The best way to find `query`'s characters in order in `text`. Consecutive characters and characters
+/// at the start of words score higher, gaps lower.
+fn fuzzy(query: &[char], text: &str, case_sensitive: bool) -> Option<(i64, Vec<Range<usize>>)> {
+    let same = |a: char, b: char| match case_sensitive {
+        true => a == b,
+        false => a.to_lowercase().eq(b.to_lowercase()),
+    };
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let first = *query.first()?;
+    let mut best: Option<(i64, Vec<usize>)> = None;
+
+    for start in (0..chars.len()).filter(|start| same(chars[*start].1, first)) {
+        let mut positions = vec![start];
+        for q in &query[1..] {
+            let from = positions.last().map(|last| last + 1).unwrap_or(0);
+            match (from..chars.len()).find(|i| same(chars[*i].1, *q)) {
+                Some(i) => positions.push(i),
+                None => break,
+            }
+        }
+        if positions.len() < query.len() {
+            // Later starts can only find fewer characters.
+            break;
+        }
+
+        let mut score = 0;
+        for (n, position) in positions.iter().enumerate() {
+            let word_start = *position == 0 || !chars[position - 1].1.is_alphanumeric();
+            score += 10;
+            if word_start {
+                score += 8;
+            }
+            if n > 0 {
+                match position - positions[n - 1] {
+                    1 => score += 5,
+                    gap => score -= (gap as i64 - 1).min(5),
+                }
+            }
+        }
+        score -= (start as i64).min(10);
+
+        if best.as_ref().is_none_or(|(best_score, _)| score > *best_score) {
+            best = Some((score, positions));
+        }
+    }
+
+    best.map(|(score, positions)| {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for position in positions {
+            let (start, c) = chars[position];
+            match ranges.last_mut() {
+                Some(range) if range.end == start => range.end = start + c.len_utf8(),
+                _ => ranges.push(start..start + c.len_utf8()),
+            }
+        }
+        (score, ranges)
+    })
+}
+
+/// Where a task matched: the field, its text and the ranges to highlight.
+pub(crate) struct FieldMatch {
+    field: &'static str,
+    text: String,
+    ranges: Vec<Range<usize>>,
+}
+
+/// A task that matched, with its rank and every matching field.
+pub(crate) struct Hit {
+    pub(crate) task: Task,
+    pub(crate) score: i64,
+    matches: Vec<FieldMatch>,
+}
+
+/// Searches `fields` of `tasks`, best matches first. Tags and annotations match one by one.
+pub(crate) fn search(tasks: &[Task], matcher: &Matcher, fields: &[&'static str]) -> Vec<Hit> {
+    let mut hits: Vec<Hit> = tasks.iter()
+        .filter_map(|task| {
+            let mut score = 0;
+            let mut matches = Vec::new();
+
+            for field in fields {
+                let texts: Vec<String> = match *field {
+                    "title" => vec![task.title.clone()],
+                    "description" => task.description.iter().cloned().collect(),
+                    "project" => task.project.iter().cloned().collect(),
+                    "tags" => task.tags.clone(),
+                    "annotations" => task.annotations.iter().map(|annotation| annotation.text.clone()).collect(),
+                    _ => Vec::new(),
+                };
+                for text in texts {
+                    if let Some((found, ranges)) = matcher.find(&text) {
+                        score = score.max(found * weight(field));
+                        matches.push(FieldMatch { field, text, ranges });
+                    }
+                }
+            }
+
+            (!matches.is_empty()).then(|| Hit { task: task.clone(), score, matches })
+        })
+        .collect();
+
+    // Stable, so equal scores keep their list order.
+    hits.sort_by_key(|hit| std::cmp::Reverse(hit.score));
+    hits
+}
+
+fn highlight(text: &str, ranges: &[Range<usize>], theme: &Theme) -> String {
+    let mut highlighted = String::new();
+    let mut end = 0;
+    for range in ranges {
+        highlighted.push_str(&text[end..range.start]);
+        highlighted.push_str(&theme.highlight.paint(&text[range.clone()]));
+        end = range.end;
+    }
+    highlighted.push_str(&text[end..]);
+    highlighted
+}
+
+/// One line per task with its title, and an indented line per match outside the title.
+pub(crate) fn hits_to_string(hits: &[Hit], theme: &Theme) -> String {
+    hits.iter()
+        .map(|hit| {
+            let title = match hit.matches.iter().find(|found| found.field == "title") {
+                Some(found) => highlight(&found.text, &found.ranges, theme),
+                None => hit.task.title.clone(),
+            };
+            let mut lines = vec![format!("{:>4}: {}", hit.task.num, title)];
+            for found in hit.matches.iter().filter(|found| found.field != "title") {
+                lines.push(format!("        {}: {}", found.field, highlight(&found.text, &found.ranges, theme)));
+            }
+            lines.join("\n")
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tasks::Status;
+    use super::*;
+
+    fn task(title: &str, tags: &[&str]) -> Task {
+        let mut task = Task::from(title.to_string(), None, None, None, None, None, None, Status::Todo, false);
+        task.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        task
+    }
+
+    fn titles(hits: &[Hit]) -> Vec<&str> {
+        hits.iter().map(|hit| hit.task.title.as_str()).collect()
+    }
+
+    /// Where `matcher` matched `text`, as (start, end) byte offsets.
+    fn ranges(matcher: &Matcher, text: &str) -> Option<Vec<(usize, usize)>> {
+        matcher.find(text).map(|(_, ranges)| ranges.into_iter().map(|range| (range.start, range.end)).collect())
+    }
+
+    #[test]
+    fn substrings_ignore_case_unless_exact() {
+        let matcher = Matcher::new("MILK", false, false, false).unwrap();
+        assert_eq!(ranges(&matcher, "Buy milk and more milk"), Some(vec![(4, 8), (18, 22)]));
+
+        let exact = Matcher::new("MILK", false, false, true).unwrap();
+        assert_eq!(ranges(&exact, "Buy milk"), None);
+    }
+
+    #[test]
+    fn substrings_are_not_patterns() {
+        let matcher = Matcher::new("a.c", false, false, false).unwrap();
+        assert_eq!(ranges(&matcher, "abc"), None);
+        assert_eq!(ranges(&matcher, "see a.c"), Some(vec![(4, 7)]));
+    }
+
+    #[test]
+    fn regular_expressions_match_patterns() {
+        let matcher = Matcher::new(r"^call \w+", true, false, false).unwrap();
+        assert_eq!(ranges(&matcher, "Call Sam back"), Some(vec![(0, 8)]));
+        assert_eq!(ranges(&matcher, "Please call Sam"), None);
+    }
+
+    #[test]
+    fn invalid_regular_expressions_explain_why_on_one_line() {
+        let error = Matcher::new("(unclosed", true, false, false).err().unwrap();
+        assert!(error.starts_with("Invalid regular expression: "), "{}", error);
+        assert!(!error.contains('\n'), "{}", error);
+    }
+
+    #[test]
+    fn fuzzy_finds_characters_in_order() {
+        let matcher = Matcher::new("grcs", false, true, false).unwrap();
+        assert_eq!(ranges(&matcher, "Buy groceries"), Some(vec![(4, 6), (7, 8), (12, 13)]));
+        assert_eq!(ranges(&matcher, "Buy GROCERIES"), Some(vec![(4, 6), (7, 8), (12, 13)]));
+        assert_eq!(ranges(&matcher, "scrg"), None);
+    }
+
+    #[test]
+    fn fuzzy_prefers_word_starts_and_runs() {
+        let matcher = Matcher::new("plan", false, true, false).unwrap();
+        let score = |text: &str| matcher.find(text).unwrap().0;
+        assert!(score("plan trip") > score("explanation"));
+        assert!(score("planet") > score("pxlxaxn"));
+    }
+
+    #[test]
+    fn titles_rank_above_other_fields() {
+        let tasks = vec![task("Pay rent", &["home"]), task("Home repairs", &[]), task("Call plumber", &[])];
+        let matcher = Matcher::new("home", false, false, false).unwrap();
+        let hits = search(&tasks, &matcher, &SEARCH_FIELDS);
+        assert_eq!(titles(&hits), vec!["Home repairs", "Pay rent"]);
+    }
+
+    #[test]
+    fn equal_scores_keep_list_order() {
+        let tasks = vec![task("Tea", &[]), task("Tea", &["b"]), task("Tea", &["c"])];
+        let matcher = Matcher::new("tea", false, false, false).unwrap();
+        let hits = search(&tasks, &matcher, &["title"]);
+        let tags: Vec<Vec<String>> = hits.iter().map(|hit| hit.task.tags.clone()).collect();
+        assert_eq!(tags, vec![vec![], vec!["b".to_string()], vec!["c".to_string()]]);
+    }
+
+    #[test]
+    fn only_the_given_fields_are_searched() {
+        let tasks = vec![task("Pay rent", &["home"])];
+        let matcher = Matcher::new("home", false, false, false).unwrap();
+        assert!(search(&tasks, &matcher, &["title"]).is_empty());
+        assert_eq!(search(&tasks, &matcher, &["tags"]).len(), 1);
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::FileHistory;
+use rustyline::validate::Validator;
+use rustyline::{CompletionType, Context, Editor, Helper};
+use crate::{Config, TaskList};
+use crate::cli::{ArgKind, ValueType};
+use crate::command_handler::{find_command, run_command, CommandError, COMMANDS};
+use crate::utils::{read_tasks, save_tasks};
+use crate::{display_command_response, display_save_response};
+
+const SHELL_COMMANDS: [&str; 5] = ["save", "autosave", "exit", "exit!", "quit"];
+
+/// Completes operations, their flags, task IDs and tags.
+struct ShellHelper {
+    /// Task IDs and titles, refreshed after every command.
+    tasks: Vec<(u8, String)>,
+    /// Every tag in use, refreshed with the tasks.
+    tags: Vec<String>,
+}
+
+impl ShellHelper {
+    fn refresh(&mut self, task_list: &TaskList) {
+        self.tasks = task_names(task_list);
+        self.tags = tag_names(task_list);
+    }
+
+    fn candidates(&self, previous: &[&str]) -> Vec<Pair> {
+        let pair = |value: &str| Pair { display: value.to_string(), replacement: format!("{} ", value) };
+
+        let command = match previous.first() {
+            None => {
+                return COMMANDS.iter()
+                    .flat_map(|command| std::iter::once(&command.name).chain(command.aliases.iter()))
+                    .chain(SHELL_COMMANDS.iter())
+                    .map(|name| pair(name))
+                    .collect();
+            }
+            Some(name) => match find_command(name) {
+                Some(command) => command,
+                None if *name == "autosave" && previous.len() == 1 => return vec![pair("on"), pair("off")],
+                None => return Vec::new(),
+            },
+        };
+
+        let mut candidates = Vec::new();
+        let ids = || self.tasks.iter().map(|(num, title)| Pair {
+            display: format!("{}  {}", num, title),
+            replacement: format!("{} ", num),
+        });
+
+        if let Some((arg, after_flag)) = command.next_arg(&previous[1..]) {
+            match arg.kind {
+                _ if arg.name == "tag" || arg.name == "untag" => candidates.extend(self.tags.iter().map(|tag| pair(tag))),
+                ArgKind::Value(ValueType::Id) => candidates.extend(ids()),
+                ArgKind::Value(ValueType::Tasks) => {
+                    candidates.extend(ids());
+                    candidates.extend(self.tags.iter().map(|tag| pair(&format!("tag:{}", tag))));
+                }
+                ArgKind::Value(ValueType::Filter) => candidates.extend(self.tags.iter().map(|tag| pair(&format!("tag:{}", tag)))),
+                ArgKind::Value(ValueType::Choice(choices)) => candidates.extend(choices.iter().map(|choice| pair(choice))),
+                ArgKind::Value(ValueType::Command) => candidates.extend(COMMANDS.iter().map(|command| pair(command.name))),
+                _ => {}
+            }
+            // The word after a flag is its value, never another flag.
+            if after_flag {
+                return candidates;
+            }
+        }
+
+        candidates.extend(command.flags().iter().map(|flag| pair(flag)));
+        candidates
+    }
+}
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        let start = before.rfind(char::is_whitespace).map(|i| i + 1).unwrap_or(0);
+        let word = &before[start..];
+        let previous: Vec<&str> = before[..start].split_whitespace().collect();
+
+        let candidates = self.candidates(&previous)
+            .into_iter()
+            .filter(|candidate| candidate.replacement.starts_with(word))
+            .collect();
+
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
+
+/// Splits a line into arguments the way a shell would, honoring quotes and backslash escapes.
+pub(crate) fn split_line(line: &str) -> Result<Vec<String>, String> {
+    let mut arguments = Vec::new();
+    let mut current = String::new();
+    let mut in_argument = false;
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => match chars.next() {
+                Some(escaped) => {
+                    current.push(escaped);
+                    in_argument = true;
+                }
+                None => return Err("Trailing backslash.".to_string()),
+            },
+            (Some(_), c) => current.push(c),
+            (None, '\'') | (None, '"') => {
+                quote = Some(c);
+                in_argument = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_argument {
+                    arguments.push(std::mem::take(&mut current));
+                    in_argument = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_argument = true;
+            }
+        }
+    }
+
+    if let Some(q) = quote {
+        return Err(format!("Unclosed quote {}.", q));
+    }
+    if in_argument {
+        arguments.push(current);
+    }
+
+    Ok(arguments)
+}
+
+pub(crate) fn history_path(config: &Config) -> PathBuf {
+    PathBuf::from(&config.data_file).with_file_name("shell_history")
+}
+
+fn task_names(task_list: &TaskList) -> Vec<(u8, String)> {
+    task_list.tasks.iter().map(|task| (task.num, task.title.clone())).collect()
+}
+
+fn tag_names(task_list: &TaskList) -> Vec<String> {
+    let tags: BTreeSet<&String> = task_list.tasks.iter().flat_map(|task| task.tags.iter()).collect();
+    tags.into_iter().cloned().collect()
+}
+
+/// Writes the task list. Successful autosaves are silent, failures are always shown.
+fn save(task_list: &mut TaskList, config: &Config, quiet: bool) {
+    let response = save_tasks(task_list, config.clone());
+    if response.is_ok() {
+        task_list.modified = false;
+        if quiet {
+            return;
+        }
+    }
+    display_save_response(response, config.clone());
+}
+
+/// Takes up settings the last command changed, e.g. with 'use' or 'config set', and switches to the
+/// current list if that's now another one. Unsaved changes are saved first, or the shell stays put.
+fn reload(task_list: &mut TaskList, config: &mut Config) {
+    let Ok(reloaded) = config.reload() else { return };
+
+    if reloaded.tasks_file() != config.tasks_file() {
+        if task_list.modified {
+            save(task_list, config, true);
+            if task_list.modified {
+                println!("Staying on list '{}' until its changes are saved.", config.current_list());
+                return;
+            }
+        }
+        *task_list = read_tasks(reloaded.clone()).unwrap_or_else(|_| TaskList::new());
+    }
+    *config = reloaded;
+}
+
+pub(crate) fn run(task_list: &mut TaskList, mut config: Config, mut autosave: bool) -> Result<String, CommandError> {
+    let editor_config = rustyline::Config::builder().completion_type(CompletionType::List).build();
+    let mut editor: Editor<ShellHelper, FileHistory> = Editor::with_config(editor_config).map_err(|error| CommandError::TerminalError(error.to_string()))?;
+    editor.set_helper(Some(ShellHelper { tasks: task_names(task_list), tags: tag_names(task_list) }));
+    let history = history_path(&config);
+    let _ = editor.load_history(&history);
+
+    println!("TaskNinja shell. Type 'help' for operations, 'exit' to leave.");
+
+    let mut warned_unsaved = false;
+
+    loop {
+        let prompt = if task_list.modified { "taskninja*> " } else { "taskninja> " };
+
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) if warned_unsaved => "exit!".to_string(),
+            Err(ReadlineError::Eof) => "exit".to_string(),
+            Err(error) => return Err(CommandError::TerminalError(error.to_string())),
+        };
+        warned_unsaved = false;
+
+        let arguments = match split_line(&line) {
+            Ok(arguments) if arguments.is_empty() => continue,
+            Ok(arguments) => arguments,
+            Err(error) => {
+                display_command_response(Err(CommandError::InvalidArgument("Shell".to_string(), error)), config.clone());
+                continue;
+            }
+        };
+        let _ = editor.add_history_entry(line.as_str());
+
+        match arguments[0].as_str() {
+            "exit" | "quit" if task_list.modified => {
+                println!("There are unsaved changes. Run 'save' first, or 'exit!' to discard them.");
+                warned_unsaved = true;
+            }
+            "exit" | "quit" => break,
+            "exit!" => {
+                task_list.modified = false;
+                break;
+            }
+            "save" => save(task_list, &config, false),
+            "autosave" => match arguments.get(1).map(|a| a.as_str()) {
+                Some("on") => {
+                    autosave = true;
+                    if task_list.modified {
+                        save(task_list, &config, true);
+                    }
+                }
+                Some("off") => autosave = false,
+                _ => println!("Autosave is {}.", if autosave { "on" } else { "off" }),
+            },
+            "shell" => println!("Already in the shell."),
+            _ => {
+                display_command_response(run_command(arguments, task_list, config.clone()), config.clone());
+                if autosave && task_list.modified {
+                    save(task_list, &config, true);
+                }
+                reload(task_list, &mut config);
+            }
+        }
+
+        if let Some(helper) = editor.helper_mut() {
+            helper.refresh(task_list);
+        }
+    }
+
+    let _ = editor.save_history(&history);
+    Ok(String::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(words: &[&str]) -> Result<Vec<String>, String> {
+        Ok(words.iter().map(|word| word.to_string()).collect())
+    }
+
+    #[test]
+    fn lines_split_on_whitespace() {
+        assert_eq!(split_line("add  Groceries \t -p high"), words(&["add", "Groceries", "-p", "high"]));
+        assert_eq!(split_line("   "), words(&[]));
+    }
+
+    #[test]
+    fn quotes_keep_words_together() {
+        assert_eq!(split_line("add 'Go on a run' due tomorrow"), words(&["add", "Go on a run", "due", "tomorrow"]));
+        assert_eq!(split_line(r#"add "Call \"Sam\"" -d 'it''s fine'"#), words(&["add", "Call \"Sam\"", "-d", "its fine"]));
+        assert_eq!(split_line("edit 1 -d ''"), words(&["edit", "1", "-d", ""]));
+        assert_eq!(split_line("add don\"'\"t"), words(&["add", "don't"]));
+    }
+
+    #[test]
+    fn backslashes_escape_outside_single_quotes() {
+        assert_eq!(split_line(r"add Buy\ milk"), words(&["add", "Buy milk"]));
+        assert_eq!(split_line(r"add 'C:\temp'"), words(&["add", r"C:\temp"]));
+    }
+
+    #[test]
+    fn unfinished_lines_are_errors() {
+        assert_eq!(split_line("add 'Go on a run"), Err("Unclosed quote '.".to_string()));
+        assert_eq!(split_line("add \"Go"), Err("Unclosed quote \".".to_string()));
+        assert_eq!(split_line("add trailing\\"), Err("Trailing backslash.".to_string()));
+    }
+
+    fn replacements(previous: &[&str]) -> Vec<String> {
+        let helper = ShellHelper { tasks: vec![(1, "One".to_string()), (2, "Two".to_string())], tags: vec!["home".to_string(), "work".to_string()] };
+        helper.candidates(previous).into_iter().map(|pair| pair.replacement.trim_end().to_string()).collect()
+    }
+
+    #[test]
+    fn completes_ids_and_tags_where_commands_take_them() {
+        assert!(replacements(&["track", "start"]).starts_with(&["1".to_string(), "2".to_string()]));
+        assert!(replacements(&["annotate", "-r", "2"]).contains(&"1".to_string()));
+        assert_eq!(replacements(&["edit", "1", "-g"]), vec!["home", "work"]);
+        assert_eq!(replacements(&["edit", "1", "--untag"]), vec!["home", "work"]);
+        assert!(replacements(&["complete"]).contains(&"tag:work".to_string()));
+        assert!(!replacements(&["annotate", "1"]).contains(&"1".to_string()));
+    }
+}
@@ -1,21 +1,250 @@
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Duration, Local, NaiveTime, TimeZone};
+use serde::{Deserialize, Deserializer, Serialize};
 
+use std::collections::BTreeMap;
 use crate::{Config, Date, Time};
-use crate::dates::DateTimeError;
+use crate::attributes::{AttributeFilter, Attributes};
+use crate::config::Urgency;
+use crate::dates::{format_duration, DateTimeError};
+use crate::filter::Term;
+use crate::theme::{Style, Theme};
+
+/// Version of the data file layout, see `TaskList::migrate`.
+pub(crate) const DATA_VERSION: u32 = 1;
+
+/// Filter names understood by `TaskList::filter_tasks`.
+pub(crate) const FILTER_NAMES: [&str; 17] = [
+    "complete", "incomplete", "flagged", "unflagged", "due_today", "visible", "hidden",
+    "todo", "in_progress", "blocked", "waiting", "done", "cancelled",
+    "high_priority", "medium_priority", "low_priority", "no_priority",
+];
+
+/// Names accepted by `Status::parse`, which are also filter names.
+pub(crate) const STATUS_NAMES: [&str; 6] = ["todo", "in_progress", "blocked", "waiting", "done", "cancelled"];
+
+/// Keys understood by `TaskList::sort_tasks`.
+pub(crate) const SORT_KEYS: [&str; 7] = ["position", "priority", "due", "urgency", "created", "modified", "completed"];
+
+/// Whether `filter` is a filter name, context or date term `TaskList::filter_tasks` understands.
+pub(crate) fn check_filter(filter: &str) -> Result<(), String> {
+    if FILTER_NAMES.contains(&filter) || (filter.starts_with('@') && filter.len() > 1) {
+        return Ok(());
+    }
+
+    match filter.contains(':') {
+        true if filter.starts_with("project:") || filter.starts_with("tag:") => Ok(()),
+        true => Term::parse(filter).map(|_| ()),
+        false => Err(format!("Unknown filter '{}'. Expected a date term like completed:last_week or one of: {}.", filter, FILTER_NAMES.join(", "))),
+    }
+}
+
+pub(crate) fn format_date(date: &Date, config: &Config) -> String {
+    match config.date_numerical {
+        true => date.as_numerical_date_string(),
+        false => date.as_calendar_date_string(),
+    }
+}
+
+pub(crate) fn format_timestamp(at: &DateTime<Local>, config: &Config) -> String {
+    match config.time_24_hour {
+        true => at.format("%Y-%m-%d %H:%M").to_string(),
+        false => at.format("%Y-%m-%d %I:%M %p").to_string(),
+    }
+}
+
+/// Where a task is in its lifecycle.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Todo,
+    InProgress,
+    Blocked,
+    Waiting,
+    Done,
+    Cancelled,
+}
+
+impl Status {
+    pub fn parse(status: &str) -> Result<Status, String> {
+        match status.to_lowercase().replace('-', "_").as_str() {
+            "todo" => Ok(Status::Todo),
+            "in_progress" => Ok(Status::InProgress),
+            "blocked" => Ok(Status::Blocked),
+            "waiting" => Ok(Status::Waiting),
+            "done" => Ok(Status::Done),
+            "cancelled" => Ok(Status::Cancelled),
+            _ => Err(format!("'{}' is not a status. Expected one of: {}.", status, STATUS_NAMES.join(", "))),
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Status::Todo => "To do",
+            Status::InProgress => "In progress",
+            Status::Blocked => "Blocked",
+            Status::Waiting => "Waiting",
+            Status::Done => "Done",
+            Status::Cancelled => "Cancelled",
+        }
+    }
+
+    /// Single character shown in front of the title in the TUI.
+    pub fn marker(&self) -> char {
+        match self {
+            Status::Todo => ' ',
+            Status::InProgress => '>',
+            Status::Blocked => '#',
+            Status::Waiting => '~',
+            Status::Done => 'x',
+            Status::Cancelled => '-',
+        }
+    }
+
+    /// Whether the task still needs work, i.e. it's neither done nor cancelled.
+    pub fn is_open(&self) -> bool {
+        !matches!(self, Status::Done | Status::Cancelled)
+    }
+}
+
+/// Reads a `Status`, or the `complete` bool that older data files stored instead.
+fn status_or_complete<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Status, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Stored {
+        Status(Status),
+        Complete(bool),
+    }
+
+    Ok(match Stored::deserialize(deserializer)? {
+        Stored::Status(status) => status,
+        Stored::Complete(true) => Status::Done,
+        Stored::Complete(false) => Status::Todo,
+    })
+}
+
+/// A change of status and when it happened.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Transition {
+    pub(crate) status: Status,
+    pub(crate) at: DateTime<Local>,
+}
+
+/// A timestamped remark added to a task with 'annotate'.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Annotation {
+    pub(crate) at: DateTime<Local>,
+    pub(crate) text: String,
+}
+
+/// Time spent on a task. A running timer has no end yet.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TimeEntry {
+    pub(crate) start: DateTime<Local>,
+    pub(crate) end: Option<DateTime<Local>>,
+}
+
+impl TimeEntry {
+    pub fn duration(&self) -> Duration {
+        self.end.unwrap_or_else(Local::now) - self.start
+    }
+}
+
+/// How important a task is. Kept separate from `Task::num`, which is only its place in the list.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    High,
+    Medium,
+    Low,
+}
+
+impl Priority {
+    pub fn parse(priority: &str) -> Result<Priority, String> {
+        match priority.to_lowercase().as_str() {
+            "high" | "h" | "1" => Ok(Priority::High),
+            "medium" | "m" | "2" => Ok(Priority::Medium),
+            "low" | "l" | "3" => Ok(Priority::Low),
+            _ => Err(format!("'{}' is not a priority. Expected high, medium, low or none.", priority)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::High => "High",
+            Priority::Medium => "Medium",
+            Priority::Low => "Low",
+        }
+    }
+
+    /// Name of the `filter_tasks` filter that keeps tasks with this priority.
+    pub fn filter_name(priority: Option<Priority>) -> &'static str {
+        match priority {
+            Some(Priority::High) => "high_priority",
+            Some(Priority::Medium) => "medium_priority",
+            Some(Priority::Low) => "low_priority",
+            None => "no_priority",
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Task {
-    num: u8,
-    title: String,
-    description: Option<String>,
-    due_date: Result<Date, DateTimeError>,
-    due_time: Result<Time, DateTimeError>,
-    pub(crate) complete: bool,
-    flagged: bool,
+    pub(crate) num: u8,
+    pub(crate) title: String,
+    pub(crate) description: Option<String>,
+    pub(crate) due_date: Result<Date, DateTimeError>,
+    pub(crate) due_time: Result<Time, DateTimeError>,
+    #[serde(default)]
+    pub(crate) priority: Option<Priority>,
+    #[serde(default)]
+    pub(crate) project: Option<String>,
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
+    /// Where or with what the task can be done, e.g. 'home' for @home.
+    #[serde(default)]
+    pub(crate) context: Option<String>,
+    /// Values of the custom attributes declared in the config, as `Attribute::parse` stores them.
+    #[serde(default)]
+    pub(crate) attributes: BTreeMap<String, String>,
+    /// When to start working on the task.
+    #[serde(default)]
+    pub(crate) scheduled: Option<Date>,
+    /// The task is hidden from 'list' until this date.
+    #[serde(default)]
+    pub(crate) wait: Option<Date>,
+    /// The task is cancelled once this date has passed.
+    #[serde(default)]
+    pub(crate) until: Option<Date>,
+    /// Expected effort in minutes.
+    #[serde(default)]
+    pub(crate) estimate_minutes: Option<i64>,
+    /// How many minutes before the task is due 'taskninja daemon' sends a reminder.
+    #[serde(default)]
+    pub(crate) remind_minutes: Option<i64>,
+    #[serde(alias = "complete", deserialize_with = "status_or_complete")]
+    pub(crate) status: Status,
+    /// Every status change, oldest first.
+    #[serde(default)]
+    pub(crate) transitions: Vec<Transition>,
+    #[serde(default = "Local::now")]
+    pub(crate) created_at: DateTime<Local>,
+    #[serde(default = "Local::now")]
+    pub(crate) modified_at: DateTime<Local>,
+    #[serde(default)]
+    pub(crate) completed_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub(crate) time_entries: Vec<TimeEntry>,
+    /// Oldest first.
+    #[serde(default)]
+    pub(crate) annotations: Vec<Annotation>,
+    /// Long-form Markdown, edited with 'edit --notes'.
+    #[serde(default)]
+    pub(crate) notes: Option<String>,
+    pub(crate) flagged: bool,
 }
 
 impl Task {
-    pub fn from(title: String, description: Option<String>, due_date: Option<Result<Date, DateTimeError>>, due_time: Option<Result<Time, DateTimeError>>, complete: bool, flagged: bool) -> Task {
+    #[allow(clippy::too_many_arguments)]
+    pub fn from(title: String, description: Option<String>, due_date: Option<Result<Date, DateTimeError>>, due_time: Option<Result<Time, DateTimeError>>, priority: Option<Priority>, project: Option<String>, estimate: Option<Duration>, status: Status, flagged: bool) -> Task {
         Task {
             num: 0,
             title,
@@ -28,39 +257,189 @@ impl Task {
                 Some(time) => time,
                 None => Err(DateTimeError::UnspecifiedTime),
             },
-            complete,
+            priority,
+            project,
+            tags: Vec::new(),
+            context: None,
+            attributes: BTreeMap::new(),
+            scheduled: None,
+            wait: None,
+            until: None,
+            estimate_minutes: estimate.map(|estimate| estimate.num_minutes()),
+            remind_minutes: None,
+            status,
+            transitions: Vec::new(),
+            created_at: Local::now(),
+            modified_at: Local::now(),
+            completed_at: match status {
+                Status::Done => Some(Local::now()),
+                _ => None,
+            },
+            time_entries: Vec::new(),
+            annotations: Vec::new(),
+            notes: None,
             flagged,
         }
     }
 
-    pub fn mark_complete(&mut self) {
-        self.complete = true;
+    /// Changes the status and records when it happened. Setting the current status again does nothing.
+    pub fn set_status(&mut self, status: Status) {
+        if self.status != status {
+            let now = Local::now();
+            self.status = status;
+            self.transitions.push(Transition { status, at: now });
+            self.modified_at = now;
+            self.completed_at = match status {
+                Status::Done => Some(now),
+                _ => None,
+            };
+        }
     }
 
-    pub fn mark_incomplete(&mut self) {
-        self.complete = false;
+    /// When the task was done or cancelled, if it is.
+    pub fn closed_at(&self) -> Option<DateTime<Local>> {
+        match self.status {
+            Status::Done => self.completed_at,
+            Status::Cancelled => Some(self.transitions.last().map(|transition| transition.at).unwrap_or(self.modified_at)),
+            _ => None,
+        }
     }
 
-    pub fn to_string(&self, config: Config) -> String {
-        let flag_color = config.flag_color;
-        let default_color = config.default_color;
-        let complete_color = config.complete_color;
-        let incomplete_color = config.incomplete_color;
+    pub fn estimate(&self) -> Option<Duration> {
+        self.estimate_minutes.map(Duration::minutes)
+    }
 
-        let mut response = String::new();
+    pub fn remind(&self) -> Option<Duration> {
+        self.remind_minutes.map(Duration::minutes)
+    }
 
-        let mut color_setup = String::new();
+    /// Whether the task belongs to `project` or one of its subprojects, e.g. 'work' matches 'work.client'.
+    pub fn in_project(&self, project: &str) -> bool {
+        match &self.project {
+            Some(own) => {
+                let (own, project) = (own.to_lowercase(), project.to_lowercase());
+                own == project || own.starts_with(&format!("{}.", project))
+            }
+            None => false,
+        }
+    }
 
-        if self.complete {
-            color_setup.push_str(&complete_color);
-        } else {
-            if self.flagged {
-                color_setup.push_str(&flag_color);
+    pub fn in_context(&self, context: &str) -> bool {
+        self.context.as_ref().is_some_and(|own| own.eq_ignore_ascii_case(context))
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|own| own.eq_ignore_ascii_case(tag))
+    }
+
+    /// Whether the task's wait date is still in the future.
+    pub fn is_hidden(&self) -> bool {
+        let today = Local::now().date_naive();
+        self.wait.as_ref().and_then(|wait| wait.to_naive_date()).is_some_and(|wait| wait > today)
+    }
+
+    /// Whether the task's until date has passed.
+    pub fn is_expired(&self) -> bool {
+        let today = Local::now().date_naive();
+        self.until.as_ref().and_then(|until| until.to_naive_date()).is_some_and(|until| until < today)
+    }
+
+    /// When the task is due. Tasks without a due time are due at the end of the day.
+    pub fn due(&self) -> Option<DateTime<Local>> {
+        let date = self.due_date.as_ref().ok()?.to_naive_date()?;
+        let time = match &self.due_time {
+            Ok(time) => time.to_naive_time()?,
+            Err(_) => NaiveTime::from_hms_opt(23, 59, 59)?,
+        };
+        Local.from_local_datetime(&date.and_time(time)).earliest()
+    }
+
+    /// How pressing the task is. Higher is more urgent; the weights come from `Config::urgency`.
+    pub fn urgency(&self, coefficients: &Urgency) -> f64 {
+        let now = Local::now();
+        let mut urgency = 0.0;
+
+        if let Some(due) = self.due() {
+            let days = (due - now).num_minutes() as f64 / (24.0 * 60.0);
+            if days < 0.0 {
+                urgency += coefficients.due + coefficients.overdue;
+            } else {
+                urgency += coefficients.due * (0.2 + 0.8 * (1.0 - days / 14.0).max(0.0));
             }
-            color_setup.push_str(&incomplete_color);
         }
 
-        response.push_str(&format!("{}\n", color_setup));
+        let scheduled = self.scheduled.as_ref().and_then(|scheduled| scheduled.to_naive_date());
+        if scheduled.is_some_and(|scheduled| scheduled <= now.date_naive()) {
+            urgency += coefficients.scheduled;
+        }
+
+        if self.flagged {
+            urgency += coefficients.flagged;
+        }
+
+        urgency += match self.priority {
+            Some(Priority::High) => coefficients.priority_high,
+            Some(Priority::Medium) => coefficients.priority_medium,
+            Some(Priority::Low) => coefficients.priority_low,
+            None => 0.0,
+        };
+
+        let age = (now - self.created_at).num_days() as f64 / 365.0;
+        urgency += coefficients.age * age.clamp(0.0, 1.0);
+
+        if !self.tags.is_empty() {
+            urgency += coefficients.tags;
+        }
+        for (tag, coefficient) in coefficients.tag.iter() {
+            if self.has_tag(tag) {
+                urgency += coefficient;
+            }
+        }
+
+        urgency += match self.status {
+            Status::InProgress => coefficients.in_progress,
+            Status::Blocked => coefficients.blocked,
+            Status::Waiting => coefficients.waiting,
+            _ => 0.0,
+        };
+
+        urgency
+    }
+
+    /// Total time tracked against the task, including a running timer.
+    pub fn tracked(&self) -> Duration {
+        self.time_entries.iter().fold(Duration::zero(), |total, entry| total + entry.duration())
+    }
+
+    pub fn timer_running(&self) -> bool {
+        self.time_entries.last().is_some_and(|entry| entry.end.is_none())
+    }
+
+    pub fn style(&self, theme: &Theme) -> Style {
+        let style = match self.status {
+            Status::Todo => theme.incomplete,
+            Status::InProgress => theme.in_progress,
+            Status::Blocked => theme.blocked,
+            Status::Waiting => theme.waiting,
+            Status::Done => theme.complete,
+            Status::Cancelled => theme.cancelled,
+        };
+
+        if self.flagged && self.status.is_open() {
+            style.patch(&theme.flag)
+        } else {
+            style
+        }
+    }
+
+    pub fn to_string(&self, config: Config) -> String {
+        let style = self.style(&config.theme());
+        format!("\n{}", style.paint(&self.to_plain_string(&config)))
+    }
+
+    /// The task details without any styling.
+    pub fn to_plain_string(&self, config: &Config) -> String {
+        let mut response = String::new();
 
         match self.num {
             0 => { response.push_str(&format!("{}\n", self.title)); }
@@ -69,7 +448,7 @@ impl Task {
 
         match &self.description {
             Some(description) => { response.push_str(&format!("Description: {}\n", description)); }
-            _ => { response.push_str(&format!("Description: Not specified.\n")); }
+            _ => { response.push_str("Description: Not specified.\n"); }
         }
 
         match &self.due_date {
@@ -81,7 +460,7 @@ impl Task {
                 }
             }
             Err(e) => match e {
-                DateTimeError::UnspecifiedDate => { response.push_str(&format!("Due Date: Not specified.\n")); }
+                DateTimeError::UnspecifiedDate => { response.push_str("Due Date: Not specified.\n"); }
                 _ => { response.push_str(&format!("Due Date: Invalid. {}\n", e)); }
             }
         }
@@ -96,31 +475,154 @@ impl Task {
             }
             Err(e) => {
                 match e {
-                    DateTimeError::UnspecifiedTime => { response.push_str(&format!("Due Time: Not specified.\n")); }
+                    DateTimeError::UnspecifiedTime => { response.push_str("Due Time: Not specified.\n"); }
                     _ => { response.push_str(&format!("Due Time: Invalid. {e}\n")); }
                 }
             }
         }
 
-        response.push_str(&format!("Complete: {}", self.complete));
+        for (label, date) in [("Scheduled", &self.scheduled), ("Wait", &self.wait), ("Until", &self.until)] {
+            if let Some(date) = date {
+                response.push_str(&format!("{}: {}\n", label, format_date(date, config)));
+            }
+        }
+
+        match &self.priority {
+            Some(priority) => { response.push_str(&format!("Priority: {}\n", priority.as_str())); }
+            None => { response.push_str("Priority: Not specified.\n"); }
+        }
+
+        if let Some(project) = &self.project {
+            response.push_str(&format!("Project: {}\n", project));
+        }
+
+        if let Some(context) = &self.context {
+            response.push_str(&format!("Context: @{}\n", context));
+        }
 
-        response.push_str(&"\x1b[0m");
-        response.push_str(&default_color);
+        if !self.tags.is_empty() {
+            response.push_str(&format!("Tags: {}\n", self.tags.join(", ")));
+        }
+
+        for (name, value) in self.attributes.iter() {
+            response.push_str(&format!("{}: {}\n", name, value));
+        }
+
+        response.push_str(&format!("Created: {}\n", format_timestamp(&self.created_at, config)));
+        response.push_str(&format!("Modified: {}\n", format_timestamp(&self.modified_at, config)));
+        if let Some(completed_at) = &self.completed_at {
+            response.push_str(&format!("Completed: {}\n", format_timestamp(completed_at, config)));
+        }
+
+        if let Some(estimate) = self.estimate() {
+            response.push_str(&format!("Estimate: {}\n", format_duration(estimate)));
+        }
+
+        if let Some(remind) = self.remind() {
+            response.push_str(&format!("Reminder: {} before due\n", format_duration(remind)));
+        }
+
+        if !self.time_entries.is_empty() {
+            let running = if self.timer_running() { " (timer running)" } else { "" };
+            response.push_str(&format!("Tracked: {}{}\n", format_duration(self.tracked()), running));
+        }
+
+        if self.status.is_open() {
+            response.push_str(&format!("Urgency: {:.1}\n", self.urgency(&config.urgency)));
+        }
+
+        match self.transitions.last() {
+            Some(transition) => response.push_str(&format!("Status: {} (since {})", self.status.label(), format_timestamp(&transition.at, config))),
+            None => response.push_str(&format!("Status: {}", self.status.label())),
+        }
+
+        if !self.annotations.is_empty() {
+            response.push_str("\nAnnotations:");
+            for (i, annotation) in self.annotations.iter().enumerate() {
+                response.push_str(&format!("\n  {}. {}  {}", i + 1, format_timestamp(&annotation.at, config), annotation.text));
+            }
+        }
+
+        if let Some(notes) = &self.notes {
+            response.push_str("\nNotes:");
+            for line in notes.lines() {
+                response.push_str(format!("\n  {}", line).trim_end());
+            }
+        }
 
         response
     }
 }
 
+/// A deleted task, kept until the trash is emptied.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Trashed {
+    pub(crate) deleted_at: DateTime<Local>,
+    pub(crate) task: Task,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct TaskList {
+    /// Layout the file was written with. Missing in files from before `DATA_VERSION` 1.
+    #[serde(default)]
+    pub(crate) version: u32,
     pub(crate) tasks: Vec<Task>,
+    /// Deleted tasks, oldest first.
+    #[serde(default)]
+    pub(crate) trash: Vec<Trashed>,
+    /// Set when the list changed since it was read and needs to be saved.
+    #[serde(skip)]
+    pub(crate) modified: bool,
+}
+
+/// Splits filter terms into built-in filters and terms on custom attributes, checking both.
+fn split_filters<'a>(filters: Vec<&'a str>, attributes: &Attributes) -> Result<(Vec<&'a str>, Vec<AttributeFilter>), String> {
+    let mut built_in = Vec::new();
+    let mut custom = Vec::new();
+
+    for filter in filters {
+        match AttributeFilter::parse(filter, attributes)? {
+            Some(attribute_filter) => custom.push(attribute_filter),
+            None => {
+                check_filter(filter)?;
+                built_in.push(filter);
+            }
+        }
+    }
+
+    Ok((built_in, custom))
 }
 
 impl TaskList {
     pub fn new() -> TaskList {
         TaskList {
-            tasks: Vec::new()
+            version: DATA_VERSION,
+            tasks: Vec::new(),
+            trash: Vec::new(),
+            modified: false,
+        }
+    }
+
+    /// Upgrades a list read from an older data file. Timestamps that weren't recorded are taken
+    /// from the status history where possible, otherwise from when the file was last written.
+    pub fn migrate(&mut self, file_modified: DateTime<Local>) {
+        if self.version >= DATA_VERSION {
+            return;
         }
+
+        for task in &mut self.tasks {
+            task.created_at = task.transitions.first().map_or(file_modified, |transition| transition.at);
+            task.modified_at = task.transitions.last().map_or(file_modified, |transition| transition.at);
+            task.completed_at = match task.status {
+                Status::Done => Some(task.transitions.iter().rev()
+                    .find(|transition| transition.status == Status::Done)
+                    .map_or(file_modified, |transition| transition.at)),
+                _ => None,
+            };
+        }
+
+        self.version = DATA_VERSION;
+        self.modified = true;
     }
 
     pub fn renew(&mut self) {
@@ -134,61 +636,62 @@ impl TaskList {
     }
 
     pub fn sort_and_renew(&mut self) {
-        self.tasks.sort_by(|a, b| a.num.cmp(&b.num));
+        self.tasks.sort_by_key(|task| task.num);
         self.renew();
     }
 
-    pub fn add_task(&mut self, task: Task, priority: Option<u8>) {
+    pub fn add_task(&mut self, task: Task) {
         let mut temp = task.clone();
+        self.modified = true;
 
-        match priority {
-            Some(priority) => {
-                temp.num = priority;
-                self.tasks.insert(priority as usize, temp);
-                self.sort_and_renew();
-            }
-            None => {
-                temp.num = self.tasks.len() as u8 + 1;
-                self.tasks.push(temp);
-            }
-        }
+        temp.num = self.tasks.len() as u8 + 1;
+        self.tasks.push(temp);
     }
 
-    pub fn new_task(&mut self, title: String, description: Option<String>, due_date: Option<Result<Date, DateTimeError>>, due_time: Option<Result<Time, DateTimeError>>, priority: Option<u8>, complete: bool, flagged: bool) {
-        self.add_task(Task::from(title, description, due_date, due_time, complete, flagged), priority);
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_task(&mut self, title: String, description: Option<String>, due_date: Option<Result<Date, DateTimeError>>, due_time: Option<Result<Time, DateTimeError>>, priority: Option<Priority>, project: Option<String>, estimate: Option<Duration>, tags: Vec<String>, status: Status, flagged: bool) {
+        let mut task = Task::from(title, description, due_date, due_time, priority, project, estimate, status, flagged);
+        task.tags = tags;
+        self.add_task(task);
         self.sort_and_renew();
     }
 
-    pub fn mark_task_complete(&mut self, index: usize) -> Result<String, String> {
-        match self.tasks.get_mut(index) {
-            Some(task) => {
-                task.mark_complete();
-                let result = Ok(format!("'{}' marked complete.", task.title));
-                result
-            }
-            None => Err(format!("Task not found: {}", index))
+    /// Moves the task at `index` to `position` (0-based) and renumbers the list.
+    pub fn move_task(&mut self, index: usize, position: usize) -> Result<String, String> {
+        if index >= self.tasks.len() {
+            return Err(format!("Task not found: {}", index));
         }
+
+        let task = self.tasks.remove(index);
+        let position = position.min(self.tasks.len());
+        let response = format!("'{}' moved to position {}.", task.title, position + 1);
+
+        self.tasks.insert(position, task);
+        self.modified = true;
+        self.renew();
+        Ok(response)
     }
 
-    pub fn mark_task_incomplete(&mut self, index: usize) -> Result<String, String> {
+    pub fn set_task_status(&mut self, index: usize, status: Status) -> Result<String, String> {
         match self.tasks.get_mut(index) {
             Some(task) => {
-                task.mark_incomplete();
-                let result = Ok(format!("'{}' marked incomplete.", task.title));
-                result
+                task.set_status(status);
+                self.modified = true;
+                Ok(format!("'{}' marked {}.", task.title, status.label().to_lowercase()))
             }
             None => Err(format!("Task not found: {}", index))
         }
     }
 
-    pub fn edit_task(&mut self, index: usize, title: Option<String>, description: Option<String>, due_date: Option<Result<Date, DateTimeError>>, due_time: Option<Result<Time, DateTimeError>>, flag: Option<bool>, priority: Option<u8>) -> Result<String, String> {
-        let result = match self.tasks.get_mut(index) {
+    #[allow(clippy::too_many_arguments)]
+    pub fn edit_task(&mut self, index: usize, title: Option<String>, description: Option<String>, due_date: Option<Result<Date, DateTimeError>>, due_time: Option<Result<Time, DateTimeError>>, flag: Option<bool>, priority: Option<Option<Priority>>, project: Option<Option<String>>, estimate: Option<Option<Duration>>) -> Result<String, String> {
+        match self.tasks.get_mut(index) {
             Some(task) => {
                 if let Some(title) = title {
                     task.title = title;
                 }
                 if let Some(description) = description {
-                    task.description = Some(description);
+                    task.description = Some(description).filter(|description| !description.is_empty());
                 }
                 if let Some(due_date) = due_date {
                     task.due_date = due_date;
@@ -200,42 +703,287 @@ impl TaskList {
                     task.flagged = flag;
                 }
                 if let Some(priority) = priority {
-                    task.num = priority;
+                    task.priority = priority;
                 }
-                let result = Ok(format!("'{}' edited.", task.title));
-                result
+                if let Some(project) = project {
+                    task.project = project;
+                }
+                if let Some(estimate) = estimate {
+                    task.estimate_minutes = estimate.map(|estimate| estimate.num_minutes());
+                }
+                task.modified_at = Local::now();
+                self.modified = true;
+                Ok(format!("'{}' edited.", task.title))
             }
             None => Err(format!("Task not found: {}", index))
-        };
-        self.sort_and_renew();
-        result
+        }
+    }
+
+    /// Sets the scheduled, wait and until dates of the task at `index`. `Some(None)` clears a date.
+    pub fn schedule_task(&mut self, index: usize, scheduled: Option<Option<Date>>, wait: Option<Option<Date>>, until: Option<Option<Date>>) -> Result<String, String> {
+        match self.tasks.get_mut(index) {
+            Some(task) => {
+                if let Some(scheduled) = scheduled {
+                    task.scheduled = scheduled;
+                }
+                if let Some(wait) = wait {
+                    task.wait = wait;
+                }
+                if let Some(until) = until {
+                    task.until = until;
+                }
+                task.modified_at = Local::now();
+                self.modified = true;
+                Ok(format!("'{}' scheduled.", task.title))
+            }
+            None => Err(format!("Task not found: {}", index))
+        }
+    }
+
+    /// Cancels open tasks whose until date has passed.
+    pub fn expire(&mut self) {
+        for task in self.tasks.iter_mut().filter(|task| task.status.is_open() && task.is_expired()) {
+            task.set_status(Status::Cancelled);
+            task.annotations.push(Annotation { at: Local::now(), text: "Expired.".to_string() });
+            self.modified = true;
+        }
+    }
+
+    /// Adds and removes tags on the task at `index`. Tags are compared case-insensitively.
+    pub fn retag_task(&mut self, index: usize, add: Vec<String>, remove: Vec<String>) -> Result<String, String> {
+        match self.tasks.get_mut(index) {
+            Some(task) => {
+                task.tags.retain(|tag| !remove.iter().any(|removed| removed.eq_ignore_ascii_case(tag)));
+                for tag in add {
+                    if !task.has_tag(&tag) {
+                        task.tags.push(tag);
+                    }
+                }
+                task.modified_at = Local::now();
+                self.modified = true;
+                Ok(format!("'{}' tagged: {}", task.title, task.tags.join(", ")))
+            }
+            None => Err(format!("Task not found: {}", index))
+        }
+    }
+
+    /// Sets how long before the task at `index` is due its reminder is sent. `None` uses the configured lead.
+    pub fn set_task_reminder(&mut self, index: usize, remind: Option<Duration>) -> Result<String, String> {
+        match self.tasks.get_mut(index) {
+            Some(task) => {
+                task.remind_minutes = remind.map(|remind| remind.num_minutes());
+                task.modified_at = Local::now();
+                self.modified = true;
+                Ok(format!("'{}' edited.", task.title))
+            }
+            None => Err(format!("Task not found: {}", index))
+        }
     }
 
+    pub fn set_task_context(&mut self, index: usize, context: Option<String>) -> Result<String, String> {
+        match self.tasks.get_mut(index) {
+            Some(task) => {
+                task.context = context;
+                task.modified_at = Local::now();
+                self.modified = true;
+                Ok(format!("'{}' edited.", task.title))
+            }
+            None => Err(format!("Task not found: {}", index))
+        }
+    }
+
+    /// Sets custom attributes on the task at `index`. A `None` value removes the attribute.
+    pub fn set_attributes(&mut self, index: usize, attributes: Vec<(String, Option<String>)>) -> Result<String, String> {
+        match self.tasks.get_mut(index) {
+            Some(task) => {
+                for (name, value) in attributes {
+                    match value {
+                        Some(value) => task.attributes.insert(name, value),
+                        None => task.attributes.remove(&name),
+                    };
+                }
+                task.modified_at = Local::now();
+                self.modified = true;
+                Ok(format!("'{}' edited.", task.title))
+            }
+            None => Err(format!("Task not found: {}", index))
+        }
+    }
+
+    pub fn annotate_task(&mut self, index: usize, text: String) -> Result<String, String> {
+        match self.tasks.get_mut(index) {
+            Some(task) => {
+                let now = Local::now();
+                task.annotations.push(Annotation { at: now, text });
+                task.modified_at = now;
+                self.modified = true;
+                Ok(format!("Annotated '{}'.", task.title))
+            }
+            None => Err(format!("Task not found: {}", index))
+        }
+    }
+
+    /// Removes annotation `number` (1-based, as shown in the task details) from the task at `index`.
+    pub fn remove_annotation(&mut self, index: usize, number: usize) -> Result<String, String> {
+        match self.tasks.get_mut(index) {
+            Some(task) if number >= 1 && number <= task.annotations.len() => {
+                let annotation = task.annotations.remove(number - 1);
+                task.modified_at = Local::now();
+                self.modified = true;
+                Ok(format!("Removed annotation '{}' from '{}'.", annotation.text, task.title))
+            }
+            Some(task) => Err(format!("'{}' has no annotation {}.", task.title, number)),
+            None => Err(format!("Task not found: {}", index))
+        }
+    }
+
+    /// Replaces the notes of the task at `index`. Blank notes remove them.
+    pub fn set_task_notes(&mut self, index: usize, notes: String) -> Result<String, String> {
+        match self.tasks.get_mut(index) {
+            Some(task) => {
+                let notes = Some(notes.trim_end().to_string()).filter(|notes| !notes.trim().is_empty());
+                if task.notes != notes {
+                    task.notes = notes;
+                    task.modified_at = Local::now();
+                    self.modified = true;
+                }
+                Ok(format!("Notes for '{}' saved.", task.title))
+            }
+            None => Err(format!("Task not found: {}", index))
+        }
+    }
+
+    /// Index of the task whose timer is running, if any.
+    pub fn active_timer(&self) -> Option<usize> {
+        self.tasks.iter().position(|task| task.timer_running())
+    }
+
+    /// Starts a timer on the task at `index`, stopping any other running timer first.
+    pub fn start_timer(&mut self, index: usize) -> Result<String, String> {
+        if index >= self.tasks.len() {
+            return Err(format!("Task not found: {}", index));
+        }
+        if self.active_timer() == Some(index) {
+            return Ok(format!("Already tracking '{}'.", self.tasks[index].title));
+        }
+
+        let mut response = String::new();
+        if let Ok(stopped) = self.stop_timer() {
+            response.push_str(&format!("{}\n", stopped));
+        }
+
+        let task = &mut self.tasks[index];
+        task.time_entries.push(TimeEntry { start: Local::now(), end: None });
+        if task.status == Status::Todo {
+            task.set_status(Status::InProgress);
+        }
+        task.modified_at = Local::now();
+        self.modified = true;
+
+        response.push_str(&format!("Started tracking '{}'.", task.title));
+        Ok(response)
+    }
+
+    pub fn stop_timer(&mut self) -> Result<String, String> {
+        let index = self.active_timer().ok_or_else(|| "No timer is running.".to_string())?;
+        let task = &mut self.tasks[index];
+
+        let now = Local::now();
+        let entry = task.time_entries.last_mut().unwrap();
+        entry.end = Some(now);
+        let duration = entry.duration();
+        task.modified_at = now;
+        self.modified = true;
+
+        Ok(format!("Stopped tracking '{}' after {}. Total: {}.", task.title, format_duration(duration), format_duration(task.tracked())))
+    }
+
+    /// Records `duration` of work on the task at `index`, ending now.
+    pub fn add_time(&mut self, index: usize, duration: Duration) -> Result<String, String> {
+        match self.tasks.get_mut(index) {
+            Some(task) => {
+                let now = Local::now();
+                let entry = TimeEntry { start: now - duration, end: Some(now) };
+                match task.timer_running() {
+                    true => {
+                        let running = task.time_entries.len() - 1;
+                        task.time_entries.insert(running, entry);
+                    }
+                    false => task.time_entries.push(entry),
+                }
+                task.modified_at = now;
+                self.modified = true;
+                Ok(format!("Added {} to '{}'. Total: {}.", format_duration(duration), task.title, format_duration(task.tracked())))
+            }
+            None => Err(format!("Task not found: {}", index))
+        }
+    }
+
+    /// Moves the task at `index` to the trash.
     pub fn remove_task(&mut self, index: usize) -> Result<String, String> {
         if index < self.tasks.len() {
             let task = self.tasks.remove(index);
+            let message = format!("'{}' moved to the trash.", task.title);
+            self.trash.push(Trashed { deleted_at: Local::now(), task });
+            self.modified = true;
             self.sort_and_renew();
-            Ok(format!("'{}' removed.", task.title))
+            Ok(message)
         } else {
             Err(format!("Task not found: {}", index))
         }
     }
 
-    pub fn to_string(&self, config: Config) -> String {
-        let mut response = String::new();
+    /// Takes the tasks at `indexes`, in ascending order, out of the list for good, e.g. to move them to another list.
+    pub fn take_tasks(&mut self, indexes: &[usize]) -> Vec<Task> {
+        let mut taken: Vec<Task> = Vec::new();
+        for &index in indexes.iter().rev() {
+            if index < self.tasks.len() {
+                taken.insert(0, self.tasks.remove(index));
+            }
+        }
 
-        for task in self.tasks.iter() {
-            response.push_str(&format!("{}\n", task.to_string(config.clone())));
+        if !taken.is_empty() {
+            self.modified = true;
+            self.sort_and_renew();
         }
+        taken
+    }
 
-        response
+    /// Moves trashed task `number` (1-based, as 'trash list' shows it) back to the end of the list.
+    pub fn restore_task(&mut self, number: usize) -> Result<String, String> {
+        if number == 0 || number > self.trash.len() {
+            return Err(format!("Trashed task not found: {}", number));
+        }
+
+        let trashed = self.trash.remove(number - 1);
+        let message = format!("'{}' restored.", trashed.task.title);
+        self.add_task(trashed.task);
+        Ok(message)
+    }
+
+    /// Deletes everything in the trash for good.
+    pub fn empty_trash(&mut self) -> String {
+        let count = self.trash.len();
+        self.trash.clear();
+        self.modified = true;
+        format!("{} trashed task{} deleted for good.", count, if count == 1 { "" } else { "s" })
+    }
+
+    /// Indexes, in ascending order, of the done and cancelled tasks closed more than `days` ago.
+    pub fn closed_before(&self, days: i64) -> Vec<usize> {
+        let cutoff = Local::now() - Duration::days(days);
+        self.tasks.iter()
+            .enumerate()
+            .filter(|(_, task)| !task.status.is_open() && task.closed_at().is_some_and(|closed_at| closed_at < cutoff))
+            .map(|(index, _)| index)
+            .collect()
     }
 
     pub fn list_tasks_complete(&self, tasks: Vec<Task>) -> Vec<Task> {
         let mut response = Vec::new();
 
         for task in tasks {
-            if task.complete {
+            if task.status == Status::Done {
                 response.push(task);
             }
         }
@@ -247,7 +995,7 @@ impl TaskList {
         let mut response = Vec::new();
 
         for task in tasks {
-            if !task.complete {
+            if task.status.is_open() {
                 response.push(task);
             }
         }
@@ -279,17 +1027,98 @@ impl TaskList {
         response
     }
 
+    pub fn list_tasks_hidden(&self, tasks: Vec<Task>, hidden: bool) -> Vec<Task> {
+        let mut response = Vec::new();
+
+        for task in tasks {
+            if task.is_hidden() == hidden {
+                response.push(task);
+            }
+        }
+
+        response
+    }
+
     pub fn list_tasks_due_today(&self, tasks: Vec<Task>) -> Vec<Task> {
         let mut response = Vec::new();
 
         for task in tasks {
-            match &task.due_date {
-                Ok(date) => {
-                    if date.is_today() {
-                        response.push(task);
-                    }
+            if let Ok(date) = &task.due_date {
+                if date.is_today() {
+                    response.push(task);
                 }
-                Err(_) => {}
+            }
+        }
+
+        response
+    }
+
+    pub fn list_tasks_with_status(&self, tasks: Vec<Task>, status: Status) -> Vec<Task> {
+        let mut response = Vec::new();
+
+        for task in tasks {
+            if task.status == status {
+                response.push(task);
+            }
+        }
+
+        response
+    }
+
+    pub fn list_tasks_with_priority(&self, tasks: Vec<Task>, priority: Option<Priority>) -> Vec<Task> {
+        let mut response = Vec::new();
+
+        for task in tasks {
+            if task.priority == priority {
+                response.push(task);
+            }
+        }
+
+        response
+    }
+
+    pub fn list_tasks_matching(&self, tasks: Vec<Task>, term: &Term) -> Vec<Task> {
+        let mut response = Vec::new();
+
+        for task in tasks {
+            if term.matches(&task) {
+                response.push(task);
+            }
+        }
+
+        response
+    }
+
+    pub fn list_tasks_in_project(&self, tasks: Vec<Task>, project: &str) -> Vec<Task> {
+        let mut response = Vec::new();
+
+        for task in tasks {
+            if task.in_project(project) {
+                response.push(task);
+            }
+        }
+
+        response
+    }
+
+    pub fn list_tasks_tagged(&self, tasks: Vec<Task>, tag: &str) -> Vec<Task> {
+        let mut response = Vec::new();
+
+        for task in tasks {
+            if task.has_tag(tag) {
+                response.push(task);
+            }
+        }
+
+        response
+    }
+
+    pub fn list_tasks_in_context(&self, tasks: Vec<Task>, context: &str) -> Vec<Task> {
+        let mut response = Vec::new();
+
+        for task in tasks {
+            if task.in_context(context) {
+                response.push(task);
             }
         }
 
@@ -297,7 +1126,12 @@ impl TaskList {
     }
 
     pub fn filter_tasks(&self, filters: Vec<&str>) -> Vec<Task> {
-        let mut response = self.tasks.clone();
+        self.filter_given_tasks(self.tasks.clone(), filters)
+    }
+
+    /// Applies `filter_tasks` filters to `tasks` instead of the whole list.
+    fn filter_given_tasks(&self, tasks: Vec<Task>, filters: Vec<&str>) -> Vec<Task> {
+        let mut response = tasks;
 
         for filter in filters.iter() {
             match filter.to_string().as_str() {
@@ -316,59 +1150,240 @@ impl TaskList {
                 "due_today" => {
                     response = self.list_tasks_due_today(response.clone());
                 }
-                _ => {}
+                "visible" => {
+                    response = self.list_tasks_hidden(response.clone(), false);
+                }
+                "hidden" => {
+                    response = self.list_tasks_hidden(response.clone(), true);
+                }
+                "todo" | "in_progress" | "blocked" | "waiting" | "done" | "cancelled" => {
+                    if let Ok(status) = Status::parse(filter) {
+                        response = self.list_tasks_with_status(response.clone(), status);
+                    }
+                }
+                "high_priority" => {
+                    response = self.list_tasks_with_priority(response.clone(), Some(Priority::High));
+                }
+                "medium_priority" => {
+                    response = self.list_tasks_with_priority(response.clone(), Some(Priority::Medium));
+                }
+                "low_priority" => {
+                    response = self.list_tasks_with_priority(response.clone(), Some(Priority::Low));
+                }
+                "no_priority" => {
+                    response = self.list_tasks_with_priority(response.clone(), None);
+                }
+                filter if filter.starts_with("tag:") => {
+                    response = self.list_tasks_tagged(response.clone(), &filter["tag:".len()..]);
+                }
+                filter if filter.starts_with('@') => {
+                    response = self.list_tasks_in_context(response.clone(), &filter[1..]);
+                }
+                filter if filter.starts_with("project:") => {
+                    response = self.list_tasks_in_project(response.clone(), &filter["project:".len()..]);
+                }
+                filter => {
+                    if let Ok(term) = Term::parse(filter) {
+                        response = self.list_tasks_matching(response.clone(), &term);
+                    }
+                }
             }
         }
 
         response
     }
 
-    pub fn filter_tasks_to_string(&self, filters: Vec<&str>, config: Config) -> String {
+    /// Like `filter_tasks`, but also understands terms on the custom attributes in `attributes`.
+    /// Fails on terms that look like attribute terms but name no declared attribute.
+    pub fn filter_tasks_with_attributes(&self, filters: Vec<&str>, attributes: &Attributes) -> Result<Vec<Task>, String> {
+        let (built_in, custom) = split_filters(filters, attributes)?;
+        let mut tasks = self.filter_tasks(built_in);
+        tasks.retain(|task| custom.iter().all(|attribute_filter| attribute_filter.matches(task)));
+        Ok(tasks)
+    }
+
+    /// Positions in the list of the tasks `filter_tasks_with_attributes` keeps. Use these rather than
+    /// `Task::num` to find the tasks again, since `num` wraps around in lists of more than 255 tasks.
+    pub fn filter_indexes_with_attributes(&self, filters: Vec<&str>, attributes: &Attributes) -> Result<Vec<usize>, String> {
+        let (built_in, custom) = split_filters(filters, attributes)?;
+        Ok(self.tasks.iter()
+            .enumerate()
+            .filter(|(_, task)| !self.filter_given_tasks(vec![(*task).clone()], built_in.clone()).is_empty())
+            .filter(|(_, task)| custom.iter().all(|attribute_filter| attribute_filter.matches(task)))
+            .map(|(index, _)| index)
+            .collect())
+    }
+
+    /// Orders tasks by one of `SORT_KEYS`. Urgent, recently modified and recently completed tasks come first,
+    /// tasks without a priority, due date or completion go last, ties keep their list position.
+    pub fn sort_tasks(&self, mut tasks: Vec<Task>, key: &str, config: &Config) -> Vec<Task> {
+        match key {
+            "priority" => tasks.sort_by_key(|task| (task.priority.is_none(), task.priority)),
+            "due" => tasks.sort_by_key(|task| (
+                task.due_date.is_err(),
+                task.due_date.as_ref().map(|date| date.sort_key()).ok(),
+                task.due_time.is_err(),
+                task.due_time.as_ref().map(|time| time.sort_key()).ok(),
+            )),
+            "urgency" => tasks.sort_by(|a, b| b.urgency(&config.urgency).total_cmp(&a.urgency(&config.urgency))),
+            "created" => tasks.sort_by_key(|task| task.created_at),
+            "modified" => tasks.sort_by_key(|task| std::cmp::Reverse(task.modified_at)),
+            "completed" => tasks.sort_by_key(|task| (task.completed_at.is_none(), task.completed_at.map(std::cmp::Reverse))),
+            key => match config.attributes.get(key) {
+                Some(attribute) => tasks.sort_by(|a, b| attribute.compare_tasks(key, a, b)),
+                None => tasks.sort_by_key(|task| task.num),
+            },
+        }
+
+        tasks
+    }
+
+    pub fn tasks_to_string(&self, tasks: Vec<Task>, config: Config) -> String {
         let mut response = String::new();
 
-        for task in self.filter_tasks(filters).iter() {
+        for task in tasks.iter() {
             response.push_str(&format!("{}\n", task.to_string(config.clone())));
         }
 
         response
     }
+}
 
-    pub fn search_tasks(&self, query: String, exact: bool) -> Vec<Task> {
-        let mut response = Vec::new();
+#[cfg(test)]
+mod tests {
+    use crate::attributes::{Attribute, AttributeType, Attributes};
+    use super::*;
 
-        let mut search = query.clone();
+    fn task(title: &str, tags: &[&str]) -> Task {
+        let mut task = Task::from(title.to_string(), None, None, None, None, None, None, Status::Todo, false);
+        task.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        task
+    }
 
-        if !exact {
-            search = query.to_lowercase();
+    fn task_list(tasks: Vec<Task>) -> TaskList {
+        let mut task_list = TaskList::new();
+        for task in tasks {
+            task_list.add_task(task);
         }
+        task_list
+    }
 
-        for task in self.tasks.iter() {
-            let mut title = task.title.clone();
-            let mut description = match &task.description {
-                Some(description) => description.clone(),
-                None => String::new()
-            };
+    fn points() -> Attributes {
+        Attributes::from([("points".to_string(), Attribute { kind: AttributeType::Number, values: Vec::new() })])
+    }
 
-            if !exact {
-                title = title.to_lowercase();
-                description = description.to_lowercase();
-            }
+    #[test]
+    fn indexes_stay_right_past_255_tasks() {
+        let mut tasks: Vec<Task> = (1..=300).map(|n| task(&format!("Task {}", n), &[])).collect();
+        tasks[0].tags = vec!["first".to_string()];
+        tasks[256].tags = vec!["late".to_string()];
+        // As read from a data file. 'add_task' can't number past 255 itself.
+        let mut task_list = TaskList::new();
+        task_list.tasks = tasks;
+        task_list.renew();
+        // Task 257 shows up with the same number as task 1.
+        assert_eq!(task_list.tasks[256].num, task_list.tasks[0].num);
 
-            if title.contains(&search) || description.contains(&search) {
-                response.push(task.clone());
-            }
-        }
+        assert_eq!(task_list.filter_indexes_with_attributes(vec!["tag:first"], &Attributes::new()), Ok(vec![0]));
+        assert_eq!(task_list.filter_indexes_with_attributes(vec!["tag:late"], &Attributes::new()), Ok(vec![256]));
+    }
 
-        response
+    #[test]
+    fn filters_narrow_each_other_down() {
+        let mut tasks = vec![task("Plan", &["work"]), task("Shop", &["home"]), task("Review", &["work"])];
+        tasks[2].flagged = true;
+        let task_list = task_list(tasks);
+
+        assert_eq!(task_list.filter_indexes_with_attributes(vec!["tag:work"], &Attributes::new()), Ok(vec![0, 2]));
+        assert_eq!(task_list.filter_indexes_with_attributes(vec!["tag:work", "flagged"], &Attributes::new()), Ok(vec![2]));
+        assert_eq!(task_list.filter_indexes_with_attributes(vec![], &Attributes::new()), Ok(vec![0, 1, 2]));
     }
 
-    pub fn search_tasks_to_string(&self, query: String, exact: bool, config: Config) -> String {
-        let mut response = String::new();
+    #[test]
+    fn attribute_filters_combine_with_built_in_ones() {
+        let mut tasks = vec![task("Small", &["work"]), task("Big", &["work"]), task("Big at home", &["home"])];
+        tasks[0].attributes.insert("points".to_string(), "1".to_string());
+        tasks[1].attributes.insert("points".to_string(), "8".to_string());
+        tasks[2].attributes.insert("points".to_string(), "8".to_string());
+        let task_list = task_list(tasks);
 
-        for task in self.search_tasks(query, exact).iter() {
-            response.push_str(&format!("{}\n", task.to_string(config.clone())));
-        }
+        assert_eq!(task_list.filter_indexes_with_attributes(vec!["points.above:3"], &points()), Ok(vec![1, 2]));
+        assert_eq!(task_list.filter_indexes_with_attributes(vec!["points.above:3", "tag:work"], &points()), Ok(vec![1]));
+        assert_eq!(task_list.filter_indexes_with_attributes(vec!["points:"], &points()), Ok(vec![]));
+    }
 
-        response
+    #[test]
+    fn indexes_match_the_filtered_tasks() {
+        let task_list = task_list(vec![task("One", &["a"]), task("Two", &[]), task("Three", &["a"])]);
+        let indexes = task_list.filter_indexes_with_attributes(vec!["tag:a"], &Attributes::new()).unwrap();
+        let titles: Vec<String> = task_list.filter_tasks_with_attributes(vec!["tag:a"], &Attributes::new()).unwrap().into_iter().map(|task| task.title).collect();
+        assert_eq!(indexes.iter().map(|index| task_list.tasks[*index].title.clone()).collect::<Vec<String>>(), titles);
+    }
+
+    fn at(time: &str) -> DateTime<Local> {
+        Local.from_local_datetime(&chrono::NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M").unwrap()).unwrap()
+    }
+
+    #[test]
+    fn old_files_get_timestamps_from_when_they_were_written() {
+        let mut task_list: TaskList = serde_json::from_value(serde_json::json!({
+            "tasks": [
+                { "num": 1, "title": "Old", "description": null, "due_date": { "Err": "UnspecifiedDate" }, "due_time": { "Err": "UnspecifiedTime" }, "complete": true, "flagged": false },
+                { "num": 2, "title": "Open", "description": null, "due_date": { "Err": "UnspecifiedDate" }, "due_time": { "Err": "UnspecifiedTime" }, "complete": false, "flagged": false },
+            ]
+        })).unwrap();
+        assert_eq!(task_list.version, 0);
+
+        task_list.migrate(at("2023-01-05 10:00"));
+
+        assert_eq!(task_list.version, DATA_VERSION);
+        assert!(task_list.modified);
+        assert_eq!(task_list.tasks[0].status, Status::Done);
+        assert_eq!(task_list.tasks[0].created_at, at("2023-01-05 10:00"));
+        assert_eq!(task_list.tasks[0].modified_at, at("2023-01-05 10:00"));
+        assert_eq!(task_list.tasks[0].completed_at, Some(at("2023-01-05 10:00")));
+        assert_eq!(task_list.tasks[1].status, Status::Todo);
+        assert_eq!(task_list.tasks[1].completed_at, None);
+    }
+
+    #[test]
+    fn status_history_dates_migrated_tasks() {
+        let mut done = task("Done", &[]);
+        done.status = Status::Done;
+        done.transitions = vec![
+            Transition { status: Status::InProgress, at: at("2023-01-01 09:00") },
+            Transition { status: Status::Done, at: at("2023-01-02 17:00") },
+            Transition { status: Status::Done, at: at("2023-01-03 08:00") },
+        ];
+        let mut task_list = task_list(vec![done]);
+        task_list.version = 0;
+
+        task_list.migrate(at("2023-02-01 12:00"));
+
+        let task = &task_list.tasks[0];
+        assert_eq!(task.created_at, at("2023-01-01 09:00"));
+        assert_eq!(task.modified_at, at("2023-01-03 08:00"));
+        assert_eq!(task.completed_at, Some(at("2023-01-03 08:00")));
+    }
+
+    #[test]
+    fn current_files_are_left_alone() {
+        let mut task_list = task_list(vec![task("New", &[])]);
+        task_list.modified = false;
+        let created_at = task_list.tasks[0].created_at;
+
+        task_list.migrate(at("2023-02-01 12:00"));
+
+        assert!(!task_list.modified);
+        assert_eq!(task_list.tasks[0].created_at, created_at);
+        assert_eq!(serde_json::to_value(&task_list).unwrap()["version"], DATA_VERSION);
+    }
+
+    #[test]
+    fn unknown_filters_are_errors() {
+        let task_list = task_list(vec![task("One", &[])]);
+        assert!(task_list.filter_indexes_with_attributes(vec!["bogus"], &Attributes::new()).is_err());
+        assert!(task_list.filter_indexes_with_attributes(vec!["due.around:today"], &Attributes::new()).is_err());
+        assert!(task_list.filter_indexes_with_attributes(vec!["points.above:many"], &points()).is_err());
     }
 }
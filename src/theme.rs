@@ -0,0 +1,305 @@
+use std::env;
+use std::fmt;
+use std::error::Error as StdError;
+use std::io::IsTerminal;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ThemeError {
+    InvalidColor(String),
+    InvalidTheme(String),
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ThemeError::InvalidColor(color) => write!(f, "Invalid color '{}'. Use a color name (e.g. 'bold cyan'), a 256-color index (e.g. 'color208') or a hex value (e.g. '#ff8800').", color),
+            ThemeError::InvalidTheme(theme) => write!(f, "Invalid theme '{}'. Available themes: dark, light.", theme),
+        }
+    }
+}
+
+impl StdError for ThemeError {
+    fn description(&self) -> &str {
+        match self {
+            ThemeError::InvalidColor(_) => "Invalid color.",
+            ThemeError::InvalidTheme(_) => "Invalid theme.",
+        }
+    }
+}
+
+const COLOR_NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    /// One of the 16 basic terminal colors. 0-7 are normal, 8-15 are bright.
+    Basic(u8),
+    /// An index into the 256-color palette.
+    Indexed(u8),
+    /// A 24-bit truecolor value.
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    pub fn parse(color: &str) -> Result<Color, ThemeError> {
+        let color = color.trim().to_lowercase();
+        let invalid = || ThemeError::InvalidColor(color.to_string());
+
+        if let Some(hex) = color.strip_prefix('#') {
+            let hex = match hex.len() {
+                3 => hex.chars().flat_map(|c| [c, c]).collect::<String>(),
+                6 => hex.to_string(),
+                _ => return Err(invalid()),
+            };
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+            return Ok(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+        }
+
+        if let Some(index) = color.strip_prefix("color") {
+            return index.parse::<u8>().map(Color::Indexed).map_err(|_| invalid());
+        }
+
+        if let Ok(index) = color.parse::<u8>() {
+            return Ok(Color::Indexed(index));
+        }
+
+        let (bright, name) = match color.strip_prefix("bright") {
+            Some(name) => (true, name.trim_start_matches(['_', '-'])),
+            None => (false, color.as_str()),
+        };
+
+        match COLOR_NAMES.iter().position(|c| *c == name) {
+            Some(i) if bright => Ok(Color::Basic(i as u8 + 8)),
+            Some(i) => Ok(Color::Basic(i as u8)),
+            None => Err(invalid()),
+        }
+    }
+
+    fn escape_code(&self, background: bool) -> String {
+        match self {
+            Color::Basic(i) if *i < 8 => format!("{}", if background { 40 } else { 30 } + *i as u32),
+            Color::Basic(i) => format!("{}", if background { 100 } else { 90 } + (*i - 8) as u32),
+            Color::Indexed(i) => format!("{};5;{}", if background { 48 } else { 38 }, i),
+            Color::Rgb(r, g, b) => format!("{};2;{};{};{}", if background { 48 } else { 38 }, r, g, b),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub inverse: bool,
+}
+
+impl Style {
+    /// Parses a style description such as `red`, `bold cyan`, `underline color208 on #202020`.
+    /// Raw ANSI escape sequences from older config files are also accepted.
+    pub fn parse(style: &str) -> Result<Style, ThemeError> {
+        if style.starts_with('\x1b') {
+            return Style::parse_escape(style);
+        }
+
+        let mut result = Style::default();
+        let mut background = false;
+
+        for word in style.split_whitespace() {
+            match word.to_lowercase().as_str() {
+                "none" | "default" => {}
+                "bold" => result.bold = true,
+                "dim" => result.dim = true,
+                "italic" => result.italic = true,
+                "underline" => result.underline = true,
+                "inverse" | "reverse" => result.inverse = true,
+                "on" => background = true,
+                _ => {
+                    let color = Color::parse(word).map_err(|_| ThemeError::InvalidColor(style.to_string()))?;
+                    if background {
+                        result.bg = Some(color);
+                        background = false;
+                    } else {
+                        result.fg = Some(color);
+                    }
+                }
+            }
+        }
+
+        if background {
+            return Err(ThemeError::InvalidColor(style.to_string()));
+        }
+
+        Ok(result)
+    }
+
+    fn parse_escape(escape: &str) -> Result<Style, ThemeError> {
+        let invalid = || ThemeError::InvalidColor(escape.escape_default().to_string());
+        let mut result = Style::default();
+
+        for sequence in escape.split('\x1b').filter(|s| !s.is_empty()) {
+            let codes = sequence.strip_prefix('[').and_then(|s| s.strip_suffix('m')).ok_or_else(invalid)?;
+            let codes = codes.split(';').map(|c| c.parse::<u8>().map_err(|_| invalid())).collect::<Result<Vec<u8>, ThemeError>>()?;
+
+            let mut i = 0;
+            while i < codes.len() {
+                match codes[i] {
+                    0 => result = Style::default(),
+                    1 => result.bold = true,
+                    2 => result.dim = true,
+                    3 => result.italic = true,
+                    4 => result.underline = true,
+                    7 => result.inverse = true,
+                    code @ 30..=37 => result.fg = Some(Color::Basic(code - 30)),
+                    code @ 40..=47 => result.bg = Some(Color::Basic(code - 40)),
+                    code @ 90..=97 => result.fg = Some(Color::Basic(code - 90 + 8)),
+                    code @ 100..=107 => result.bg = Some(Color::Basic(code - 100 + 8)),
+                    code @ (38 | 48) => {
+                        let color = match codes.get(i + 1) {
+                            Some(5) if i + 2 < codes.len() => {
+                                i += 2;
+                                Color::Indexed(codes[i])
+                            }
+                            Some(2) if i + 4 < codes.len() => {
+                                i += 4;
+                                Color::Rgb(codes[i - 2], codes[i - 1], codes[i])
+                            }
+                            _ => return Err(invalid()),
+                        };
+                        if code == 38 {
+                            result.fg = Some(color);
+                        } else {
+                            result.bg = Some(color);
+                        }
+                    }
+                    _ => return Err(invalid()),
+                }
+                i += 1;
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Layers `other` on top of this style. Colors set in `other` win, attributes are combined.
+    pub fn patch(&self, other: &Style) -> Style {
+        Style {
+            fg: other.fg.or(self.fg),
+            bg: other.bg.or(self.bg),
+            bold: self.bold || other.bold,
+            dim: self.dim || other.dim,
+            italic: self.italic || other.italic,
+            underline: self.underline || other.underline,
+            inverse: self.inverse || other.inverse,
+        }
+    }
+
+    pub fn is_plain(&self) -> bool {
+        *self == Style::default()
+    }
+
+    fn escape(&self) -> String {
+        let mut codes = Vec::new();
+
+        if self.bold { codes.push("1".to_string()); }
+        if self.dim { codes.push("2".to_string()); }
+        if self.italic { codes.push("3".to_string()); }
+        if self.underline { codes.push("4".to_string()); }
+        if self.inverse { codes.push("7".to_string()); }
+        if let Some(fg) = &self.fg { codes.push(fg.escape_code(false)); }
+        if let Some(bg) = &self.bg { codes.push(bg.escape_code(true)); }
+
+        format!("\x1b[{}m", codes.join(";"))
+    }
+
+    /// Wraps `text` in this style's escape sequences. Plain styles return the text unchanged.
+    pub fn paint(&self, text: &str) -> String {
+        if self.is_plain() {
+            text.to_string()
+        } else {
+            format!("{}{}\x1b[0m", self.escape(), text)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub error: Style,
+    pub flag: Style,
+    pub success: Style,
+    pub default: Style,
+    pub complete: Style,
+    pub incomplete: Style,
+    pub in_progress: Style,
+    pub blocked: Style,
+    pub waiting: Style,
+    pub cancelled: Style,
+    /// Matches in 'search' results.
+    pub highlight: Style,
+}
+
+impl Theme {
+    pub fn dark() -> Theme {
+        Theme {
+            error: Style::parse("red").unwrap(),
+            flag: Style::parse("underline").unwrap(),
+            success: Style::parse("green").unwrap(),
+            default: Style::default(),
+            complete: Style::parse("cyan").unwrap(),
+            incomplete: Style::parse("red").unwrap(),
+            in_progress: Style::parse("bold yellow").unwrap(),
+            blocked: Style::parse("magenta").unwrap(),
+            waiting: Style::parse("blue").unwrap(),
+            cancelled: Style::parse("dim").unwrap(),
+            highlight: Style::parse("bold yellow").unwrap(),
+        }
+    }
+
+    pub fn light() -> Theme {
+        Theme {
+            error: Style::parse("bold color124").unwrap(),
+            flag: Style::parse("underline").unwrap(),
+            success: Style::parse("color28").unwrap(),
+            default: Style::default(),
+            complete: Style::parse("blue").unwrap(),
+            incomplete: Style::parse("color124").unwrap(),
+            in_progress: Style::parse("bold color130").unwrap(),
+            blocked: Style::parse("color90").unwrap(),
+            waiting: Style::parse("color25").unwrap(),
+            cancelled: Style::parse("dim").unwrap(),
+            highlight: Style::parse("bold color25").unwrap(),
+        }
+    }
+
+    /// A theme without any styling, used when colors are disabled.
+    pub fn plain() -> Theme {
+        Theme {
+            error: Style::default(),
+            flag: Style::default(),
+            success: Style::default(),
+            default: Style::default(),
+            complete: Style::default(),
+            incomplete: Style::default(),
+            in_progress: Style::default(),
+            blocked: Style::default(),
+            waiting: Style::default(),
+            cancelled: Style::default(),
+            highlight: Style::default(),
+        }
+    }
+
+    pub fn by_name(name: &str) -> Result<Theme, ThemeError> {
+        match name.to_lowercase().as_str() {
+            "dark" => Ok(Theme::dark()),
+            "light" => Ok(Theme::light()),
+            _ => Err(ThemeError::InvalidTheme(name.to_string())),
+        }
+    }
+}
+
+/// Colors are only written when stdout is a terminal and `NO_COLOR` is unset or empty.
+pub fn colors_enabled() -> bool {
+    let no_color = env::var_os("NO_COLOR").map(|v| !v.is_empty()).unwrap_or(false);
+    !no_color && std::io::stdout().is_terminal()
+}
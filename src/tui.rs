@@ -0,0 +1,380 @@
+use std::io::{stdout, Stdout, Write};
+use crossterm::{cursor, execute, queue, terminal};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::Print;
+use crate::{Config, Date, Time, TaskList};
+use crate::command_handler::{run_modify_hooks, CommandError};
+use crate::dates::DateTimeError;
+use crate::filter::parse_date;
+use crate::tasks::{Status, Task};
+use crate::theme::{Style, Theme};
+use crate::utils::save_tasks;
+
+const KEY_HELP: &str = "j/k move  space done  s/b/w/x status  f flag  t title  d description  D due  / filter  q quit";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Title,
+    Description,
+    Due,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Normal,
+    Filter,
+    Edit(Field),
+}
+
+/// Restores the terminal when the TUI exits, including on panic.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter(out: &mut Stdout) -> std::io::Result<TerminalGuard> {
+        terminal::enable_raw_mode()?;
+        execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+struct App<'a> {
+    task_list: &'a mut TaskList,
+    config: Config,
+    theme: Theme,
+    filters: Vec<String>,
+    visible: Vec<Task>,
+    /// Positions in `TaskList::tasks` of the tasks in `visible`.
+    indexes: Vec<usize>,
+    selected: usize,
+    offset: usize,
+    mode: Mode,
+    input: String,
+    message: Option<Result<String, String>>,
+    quit: bool,
+}
+
+impl<'a> App<'a> {
+    fn new(task_list: &'a mut TaskList, config: Config) -> App<'a> {
+        let mut app = App {
+            task_list,
+            theme: config.theme(),
+            config,
+            filters: vec!["visible".to_string()],
+            visible: Vec::new(),
+            indexes: Vec::new(),
+            selected: 0,
+            offset: 0,
+            mode: Mode::Normal,
+            input: String::new(),
+            message: None,
+            quit: false,
+        };
+        app.refresh();
+        app
+    }
+
+    fn refresh(&mut self) {
+        self.indexes = self.task_list.filter_indexes_with_attributes(self.filters.iter().map(|f| f.as_str()).collect(), &self.config.attributes)
+            .unwrap_or_default();
+        self.visible = self.indexes.iter().map(|index| self.task_list.tasks[*index].clone()).collect();
+        if self.selected >= self.visible.len() {
+            self.selected = self.visible.len().saturating_sub(1);
+        }
+    }
+
+    /// Index into `TaskList::tasks` of the selected task.
+    fn selected_index(&self) -> Option<usize> {
+        self.indexes.get(self.selected).copied()
+    }
+
+    /// Runs the modify hooks on what changed since `before`, as the command line does, then writes the tasks.
+    /// A change a hook rejects is undone and not written.
+    fn save(&mut self, before: Vec<Task>, result: Result<String, String>) {
+        let result = result.and_then(|message| match run_modify_hooks(self.task_list, before, &self.config) {
+            Ok(feedback) => Ok(std::iter::once(message).chain(feedback).collect::<Vec<String>>().join(" ")),
+            Err(error) => Err(error.to_string()),
+        });
+
+        self.message = match result {
+            Ok(message) => match save_tasks(self.task_list, self.config.clone()) {
+                Ok(_) => Some(Ok(message)),
+                Err(error) => Some(Err(error.to_string())),
+            },
+            Err(error) => Some(Err(error)),
+        };
+        self.refresh();
+    }
+
+    /// Sets the selected task to `status`, or back to todo if it already has it.
+    fn toggle_status(&mut self, status: Status) {
+        if let Some(index) = self.selected_index() {
+            let before = self.task_list.tasks.clone();
+            let status = if self.task_list.tasks[index].status == status { Status::Todo } else { status };
+            let result = self.task_list.set_task_status(index, status);
+            self.save(before, result);
+        }
+    }
+
+    fn toggle_flag(&mut self) {
+        if let Some(index) = self.selected_index() {
+            let before = self.task_list.tasks.clone();
+            let flag = !self.task_list.tasks[index].flagged;
+            let result = self.task_list.edit_task(index, None, None, None, None, Some(flag), None, None, None);
+            self.save(before, result);
+        }
+    }
+
+    fn start_edit(&mut self, field: Field) {
+        let task = match self.selected_index() {
+            Some(index) => &self.task_list.tasks[index],
+            None => return,
+        };
+
+        self.input = match field {
+            Field::Title => task.title.clone(),
+            Field::Description => task.description.clone().unwrap_or_default(),
+            Field::Due => {
+                let mut due = String::new();
+                if let Ok(date) = &task.due_date {
+                    due.push_str(&date.as_iso_date_string());
+                }
+                if let Ok(time) = &task.due_time {
+                    due.push_str(&format!(" {}", time.as_24_hour_time_string()));
+                }
+                due.trim().to_string()
+            }
+        };
+        self.mode = Mode::Edit(field);
+    }
+
+    fn commit_edit(&mut self, field: Field) {
+        let index = match self.selected_index() {
+            Some(index) => index,
+            None => return,
+        };
+        let input = self.input.trim().to_string();
+        let before = self.task_list.tasks.clone();
+
+        let result = match field {
+            Field::Title if input.is_empty() => Err("Title cannot be empty.".to_string()),
+            Field::Title => self.task_list.edit_task(index, Some(input), None, None, None, None, None, None, None),
+            // An empty description clears it.
+            Field::Description => self.task_list.edit_task(index, None, Some(input), None, None, None, None, None, None),
+            Field::Due => match parse_due(&input) {
+                Ok((date, time)) => self.task_list.edit_task(index, None, None, Some(date), Some(time), None, None, None, None),
+                Err(error) => Err(format!("Invalid due date: {}", error)),
+            },
+        };
+
+        self.save(before, result);
+    }
+
+    fn commit_filter(&mut self) {
+        let filters: Vec<String> = self.input.split_whitespace().map(|f| f.to_lowercase()).collect();
+
+        match self.task_list.filter_tasks_with_attributes(filters.iter().map(|f| f.as_str()).collect(), &self.config.attributes) {
+            Err(error) => {
+                self.message = Some(Err(error));
+            }
+            Ok(_) => {
+                self.filters = filters;
+                self.selected = 0;
+                self.message = None;
+                self.refresh();
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, list_height: usize) {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return;
+        }
+
+        match self.mode {
+            Mode::Normal => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+                KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+                KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+                KeyCode::PageDown => self.move_selection(list_height as isize),
+                KeyCode::PageUp => self.move_selection(-(list_height as isize)),
+                KeyCode::Home | KeyCode::Char('g') => self.selected = 0,
+                KeyCode::End | KeyCode::Char('G') => self.selected = self.visible.len().saturating_sub(1),
+                KeyCode::Char(' ') | KeyCode::Char('c') => self.toggle_status(Status::Done),
+                KeyCode::Char('s') => self.toggle_status(Status::InProgress),
+                KeyCode::Char('b') => self.toggle_status(Status::Blocked),
+                KeyCode::Char('w') => self.toggle_status(Status::Waiting),
+                KeyCode::Char('x') => self.toggle_status(Status::Cancelled),
+                KeyCode::Char('f') => self.toggle_flag(),
+                KeyCode::Char('t') => self.start_edit(Field::Title),
+                KeyCode::Char('d') => self.start_edit(Field::Description),
+                KeyCode::Char('D') => self.start_edit(Field::Due),
+                KeyCode::Char('/') => {
+                    self.input = self.filters.join(" ");
+                    self.mode = Mode::Filter;
+                }
+                _ => {}
+            },
+            Mode::Filter | Mode::Edit(_) => match key.code {
+                KeyCode::Esc => self.mode = Mode::Normal,
+                KeyCode::Backspace => { self.input.pop(); }
+                KeyCode::Char(c) => self.input.push(c),
+                KeyCode::Enter => {
+                    match self.mode {
+                        Mode::Filter => self.commit_filter(),
+                        Mode::Edit(field) => self.commit_edit(field),
+                        Mode::Normal => {}
+                    }
+                    self.mode = Mode::Normal;
+                }
+                _ => {}
+            },
+        }
+    }
+
+    fn move_selection(&mut self, amount: isize) {
+        let last = self.visible.len().saturating_sub(1) as isize;
+        self.selected = (self.selected as isize + amount).clamp(0, last) as usize;
+    }
+
+    fn draw(&mut self, out: &mut Stdout) -> std::io::Result<()> {
+        let (width, height) = terminal::size()?;
+        let (width, height) = (width as usize, height as usize);
+        let list_height = height.saturating_sub(2);
+        let list_width = if width >= 80 { width * 3 / 5 } else { width };
+
+        if self.selected < self.offset {
+            self.offset = self.selected;
+        } else if self.selected >= self.offset + list_height {
+            self.offset = self.selected + 1 - list_height;
+        }
+
+        queue!(out, terminal::Clear(terminal::ClearType::All))?;
+
+        let filters = if self.filters.is_empty() { "none".to_string() } else { self.filters.join(" ") };
+        let header = format!(" TaskNinja  {} tasks  Filter: {}", self.visible.len(), filters);
+        let header_style = Style { inverse: true, ..Style::default() };
+        queue!(out, cursor::MoveTo(0, 0), Print(header_style.paint(&fit(&header, width))))?;
+
+        for row in 0..list_height {
+            let line = match self.visible.get(self.offset + row) {
+                Some(task) => {
+                    let due = match &task.due_date {
+                        Ok(date) => date.as_iso_date_string(),
+                        Err(_) => String::new(),
+                    };
+                    let priority = task.priority.map(|priority| &priority.as_str()[..1]).unwrap_or(" ");
+                    let text = format!("{:>3} [{}]{} {} {}", task.num, task.status.marker(), if task.flagged { '!' } else { ' ' }, priority, task.title);
+                    let due_width = due.chars().count();
+                    let text = if list_width > due_width + 1 {
+                        format!("{} {}", fit(&text, list_width - due_width - 1), due)
+                    } else {
+                        fit(&text, list_width)
+                    };
+
+                    let mut style = task.style(&self.theme);
+                    if self.offset + row == self.selected {
+                        style = style.patch(&Style { inverse: true, ..Style::default() });
+                    }
+                    style.paint(&text)
+                }
+                None => String::new(),
+            };
+            queue!(out, cursor::MoveTo(0, row as u16 + 1), Print(line))?;
+        }
+
+        if list_width < width {
+            let detail_width = width - list_width - 2;
+            let details = match self.visible.get(self.selected) {
+                Some(task) => task.to_plain_string(&self.config),
+                None => "No tasks.".to_string(),
+            };
+            let lines: Vec<String> = details.lines().flat_map(|line| wrap(line, detail_width)).collect();
+
+            for row in 0..list_height {
+                let line = lines.get(row).map(|line| line.as_str()).unwrap_or("");
+                queue!(out, cursor::MoveTo(list_width as u16, row as u16 + 1), Print(format!("│ {}", line)))?;
+            }
+        }
+
+        let (footer, footer_style) = match self.mode {
+            Mode::Filter => (format!("Filter (e.g. incomplete flagged completed:this_week): {}", self.input), Style::default()),
+            Mode::Edit(Field::Title) => (format!("Title: {}", self.input), Style::default()),
+            Mode::Edit(Field::Description) => (format!("Description: {}", self.input), Style::default()),
+            Mode::Edit(Field::Due) => (format!("Due (e.g. 2024-05-01 09:00, tomorrow or +3d): {}", self.input), Style::default()),
+            Mode::Normal => match &self.message {
+                Some(Ok(message)) => (message.clone(), self.theme.success),
+                Some(Err(error)) => (error.clone(), self.theme.error),
+                None => (KEY_HELP.to_string(), Style::default()),
+            },
+        };
+        queue!(out, cursor::MoveTo(0, height.saturating_sub(1) as u16), Print(footer_style.paint(&fit(&footer, width))))?;
+
+        if self.mode == Mode::Normal {
+            queue!(out, cursor::Hide)?;
+        } else {
+            queue!(out, cursor::Show)?;
+        }
+
+        out.flush()
+    }
+}
+
+type Due = (Result<Date, DateTimeError>, Result<Time, DateTimeError>);
+
+/// Parses a date as the command line takes it, e.g. 2024-05-01, tomorrow or +3d, and an optional HH:MM.
+/// An empty string clears the due date and time.
+fn parse_due(input: &str) -> Result<Due, String> {
+    let parts: Vec<&str> = input.split_whitespace().collect();
+    match parts.as_slice() {
+        [] => Ok((Err(DateTimeError::UnspecifiedDate), Err(DateTimeError::UnspecifiedTime))),
+        [date] => Ok((Ok(parse_date(date)?), Err(DateTimeError::UnspecifiedTime))),
+        [date, time] => Ok((Ok(parse_date(date)?), Ok(Time::parse(time).map_err(|error| error.to_string())?))),
+        _ => Err(DateTimeError::UnspecifiedDate.to_string()),
+    }
+}
+
+/// Truncates `text` to `width` characters.
+fn fit(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+/// Breaks `text` into lines of at most `width` characters.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    if width == 0 {
+        return Vec::new();
+    }
+    let chars: Vec<char> = text.chars().collect();
+    if chars.is_empty() {
+        return vec![String::new()];
+    }
+    chars.chunks(width).map(|chunk| chunk.iter().collect()).collect()
+}
+
+pub(crate) fn run(task_list: &mut TaskList, config: Config) -> Result<String, CommandError> {
+    let mut out = stdout();
+    let terminal_error = |error: std::io::Error| CommandError::TerminalError(error.to_string());
+
+    let _guard = TerminalGuard::enter(&mut out).map_err(terminal_error)?;
+    let mut app = App::new(task_list, config);
+
+    while !app.quit {
+        app.draw(&mut out).map_err(terminal_error)?;
+
+        if let Event::Key(key) = event::read().map_err(terminal_error)? {
+            if key.kind == KeyEventKind::Press {
+                let list_height = terminal::size().map_err(terminal_error)?.1.saturating_sub(2) as usize;
+                app.handle_key(key, list_height);
+            }
+        }
+    }
+
+    Ok(String::new())
+}
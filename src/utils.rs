@@ -1,5 +1,7 @@
+use chrono::{DateTime, Local};
 use serde_json::to_writer_pretty;
 use std::fs::File;
+use std::path::Path;
 use crate::{Config, TaskList};
 use std::fmt;
 use std::error::Error as StdError;
@@ -30,12 +32,17 @@ impl StdError for SaveError {
 }
 
 pub(crate) fn read_tasks(config: Config) -> Result<TaskList, SaveError> {
-    let file = File::open(config.data_file);
+    let file = File::open(config.tasks_file());
     match file {
         Ok(file) => {
-            let task_list = serde_json::from_reader(file);
+            let file_modified = file.metadata().and_then(|metadata| metadata.modified()).map(DateTime::<Local>::from).unwrap_or_else(|_| Local::now());
+            let task_list: Result<TaskList, _> = serde_json::from_reader(file);
             match task_list {
-                Ok(task_list) => Ok(task_list),
+                Ok(mut task_list) => {
+                    task_list.migrate(file_modified);
+                    task_list.expire();
+                    Ok(task_list)
+                }
                 Err(error) => Err(SaveError::FailedRead(error.to_string())),
             }
         }
@@ -43,21 +50,38 @@ pub(crate) fn read_tasks(config: Config) -> Result<TaskList, SaveError> {
     }
 }
 
-pub(crate) fn save_tasks(tasks: TaskList, config: Config) -> Result<String, SaveError> {
-    let file = File::create(&config.data_file);
-    match file {
-        Ok(file) => {
-            let write = to_writer_pretty(file, &tasks);
-            match write {
-                Ok(_) => Ok("Tasks saved successfully.".to_string()),
-                Err(error) => Err(SaveError::FailedSave(error.to_string())),
-            }
-        }
-        Err(_) => {
-            let _ = std::fs::create_dir("data");
-            let file = File::create(&config.data_file);
-            let _ = to_writer_pretty(file.unwrap(), &tasks);
-            Err(SaveError::FailedSave("Could not find data file. Using default location.".to_string()))
-        }
+/// Reads the archive 'archive' moves old tasks to. A missing archive is empty.
+pub(crate) fn read_archive(config: &Config) -> Result<TaskList, SaveError> {
+    match File::open(&config.archive_file) {
+        Ok(file) => serde_json::from_reader(file).map_err(|error| SaveError::FailedRead(format!("{}: {}", config.archive_file, error))),
+        Err(_) => Ok(TaskList::new()),
+    }
+}
+
+/// Writes the archive, creating its directory first if needed.
+pub(crate) fn save_archive(archive: &TaskList, config: &Config) -> Result<String, SaveError> {
+    create_parent_dir(&config.archive_file)?;
+    let file = File::create(&config.archive_file).map_err(|error| SaveError::FailedSave(format!("{}: {}", config.archive_file, error)))?;
+    to_writer_pretty(file, archive)
+        .map(|_| "Archive saved successfully.".to_string())
+        .map_err(|error| SaveError::FailedSave(format!("{}: {}", config.archive_file, error)))
+}
+
+fn create_parent_dir(file: &str) -> Result<(), SaveError> {
+    match Path::new(file).parent().filter(|directory| !directory.as_os_str().is_empty()) {
+        Some(directory) => std::fs::create_dir_all(directory).map_err(|error| SaveError::FailedSave(format!("{}: {}", directory.display(), error))),
+        None => Ok(()),
+    }
+}
+
+/// Writes the current list's data file, creating its directory first if needed.
+pub(crate) fn save_tasks(tasks: &TaskList, config: Config) -> Result<String, SaveError> {
+    let data_file = config.tasks_file();
+    create_parent_dir(&data_file)?;
+
+    let file = File::create(&data_file).map_err(|error| SaveError::FailedSave(format!("{}: {}", data_file, error)))?;
+    match to_writer_pretty(file, tasks) {
+        Ok(_) => Ok("Tasks saved successfully.".to_string()),
+        Err(error) => Err(SaveError::FailedSave(error.to_string())),
     }
 }
\ No newline at end of file